@@ -0,0 +1,34 @@
+use clap::Parser;
+
+/// 🔬 Debug Pixel Command
+///
+/// This command prints the orbit of a single point 🪐, to see why a fractal looks wrong there.
+#[derive(Parser, Debug)]
+#[command(name = "debug-pixel", about = "🔬 Print the iterations of a single point.", long_about = None)]
+pub struct DebugPixelCommand {
+    /// 🌀 Fractal
+    ///
+    /// The fractal to iterate, e.g. mandelbrot or julia 🔍.
+    /// Default is mandelbrot if not specified.
+    #[arg(short, long, value_name = "FRACTAL")]
+    pub fractal: Option<String>,
+
+    /// ↔️ Real part
+    ///
+    /// The x coordinate of the point in the complex plane.
+    #[arg(short, long, value_name = "X", allow_negative_numbers = true)]
+    pub x: f64,
+
+    /// ↕️ Imaginary part
+    ///
+    /// The y coordinate of the point in the complex plane.
+    #[arg(short, long, value_name = "Y", allow_negative_numbers = true)]
+    pub y: f64,
+
+    /// 🔁 Maximum iterations
+    ///
+    /// The most iterations to trace 🧮.
+    /// Default is the server's starting iteration count if not specified.
+    #[arg(short, long, value_name = "ITERATIONS")]
+    pub max_iterations: Option<u32>,
+}
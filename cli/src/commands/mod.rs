@@ -1,7 +1,13 @@
-use self::{server::ServerCommand, worker::WorkerCommand};
+use self::{
+    debug_pixel::DebugPixelCommand, render::RenderCommand, server::ServerCommand,
+    status::StatusCommand, worker::WorkerCommand,
+};
 use clap::{Parser, Subcommand, ValueEnum};
 
+pub mod debug_pixel;
+pub mod render;
 pub mod server;
+pub mod status;
 pub mod worker;
 
 #[derive(Debug, ValueEnum, Clone)]
@@ -19,6 +25,13 @@ impl ToString for LogLevel {
     }
 }
 
+#[derive(Debug, ValueEnum, Clone, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
 /// 🌟 Frakt CLI
 ///
 /// The command center for managing and controlling the Frakt application 🎮.
@@ -39,7 +52,18 @@ pub struct Cli {
     #[clap(long, default_value = "info", value_name = "LEVEL")]
     pub log_level: LogLevel,
 
-    #[clap(short, long)]
+    /// 🧾 Log Format
+    ///
+    /// Print logs as colored text or as one JSON object per line 🤖.
+    /// Options: text, json.
+    #[clap(long, value_enum, default_value_t, value_name = "FORMAT")]
+    pub log_format: LogFormat,
+
+    /// ⚙️ Config file
+    ///
+    /// Path to a TOML file providing defaults for the server and worker options 🗂️.
+    /// Command-line arguments take precedence. Default is ./config.toml if it exists.
+    #[clap(short, long, value_name = "PATH")]
     pub config: Option<std::path::PathBuf>,
 }
 
@@ -54,4 +78,19 @@ pub enum Commands {
     ///
     /// Launch one or multiple worker(s) to perform assigned tasks and computations.
     Worker(WorkerCommand),
+
+    /// 🩺 Server Status
+    ///
+    /// Print the queue depth, workers, fractal and range of a running server.
+    Status(StatusCommand),
+
+    /// 🖌️ Local Render
+    ///
+    /// Compute a fractal in this process and save it as a PNG, without any networking.
+    Render(RenderCommand),
+
+    /// 🔬 Debug Pixel
+    ///
+    /// Print every iteration of a single point of a fractal, without any networking.
+    DebugPixel(DebugPixelCommand),
 }
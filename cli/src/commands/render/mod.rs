@@ -0,0 +1,47 @@
+use clap::Parser;
+
+/// 🖌️ Render Command
+///
+/// This command computes a whole image in this process 🧮, without a server or workers.
+#[derive(Parser, Debug)]
+#[command(name = "render", about = "🖌️ Render a fractal to a PNG file locally.", long_about = None)]
+pub struct RenderCommand {
+    /// 🌀 Fractal
+    ///
+    /// The fractal to render, e.g. mandelbrot or julia 🔍.
+    /// Default is mandelbrot if not specified.
+    #[arg(short, long, value_name = "FRACTAL")]
+    pub fractal: Option<String>,
+
+    /// 🎨 Palette
+    ///
+    /// The color palette to paint the fractal with 🖍️.
+    #[arg(long, value_name = "PALETTE")]
+    pub palette: Option<String>,
+
+    /// 🎲 Seed
+    ///
+    /// Picks the palette when none is named, the same seed always gives the same image.
+    #[arg(long, value_name = "SEED")]
+    pub seed: Option<u64>,
+
+    /// 📏 Image width
+    ///
+    /// Width of the rendered image in pixels.
+    /// Default is 300 if not specified.
+    #[arg(long, value_name = "WIDTH")]
+    pub width: Option<u32>,
+
+    /// 📐 Image height
+    ///
+    /// Height of the rendered image in pixels.
+    /// Default is 300 if not specified.
+    #[arg(long, value_name = "HEIGHT")]
+    pub height: Option<u32>,
+
+    /// 💾 Output file
+    ///
+    /// Path of the PNG file to write 🖼️.
+    #[arg(short, long, value_name = "PATH")]
+    pub out: std::path::PathBuf,
+}
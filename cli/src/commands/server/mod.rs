@@ -1,4 +1,6 @@
 use clap::Parser;
+use complex_rs::complex::Complex;
+use shared::models::pixel::pixel_intensity::IntensityFormat;
 
 /// 🖥️ Server Command
 ///
@@ -44,6 +46,115 @@ pub struct ServerCommand {
     #[arg(long, value_name = "TILES")]
     pub tiles: Option<u32>,
 
+    /// 🖼️ Headless rendering
+    ///
+    /// Render a single frame to a PNG file 💾 instead of opening a window.
+    /// The server exits once every tile has been rendered.
+    #[arg(long, value_name = "PATH")]
+    pub render_to: Option<std::path::PathBuf>,
+
+    /// 🎬 Frame export
+    ///
+    /// Render a sequence of frames into numbered PNGs 🖼️ in this directory, without a window.
+    /// Frames follow the Julia animation if one is given, or zoom into the center of the view.
+    #[arg(long, value_name = "DIR")]
+    pub export_frames: Option<std::path::PathBuf>,
+
+    /// 🔢 Frame count
+    ///
+    /// Number of frames written by `--export-frames`.
+    #[arg(long, value_name = "COUNT")]
+    pub frames: Option<u32>,
+
+    /// 💾 View state file
+    ///
+    /// Restore the fractal, palette and view from this file on startup 🧭,
+    /// and save them back to it when the window is closed.
+    #[arg(long, value_name = "PATH")]
+    pub state_file: Option<std::path::PathBuf>,
+
+    /// 🗺️ Plan only
+    ///
+    /// Print how the view would be tiled 🧩 and exit,
+    /// without starting the network server or opening a window.
+    #[arg(long)]
+    pub plan: bool,
+
+    /// 🎞️ Julia animation
+    ///
+    /// Sweep the Julia constant through these points, one per frame 🌀,
+    /// given as `re,im` pairs separated by semicolons, e.g. `0.285,0.013;-0.8,0.156`.
+    #[arg(long, value_name = "POINTS", value_delimiter = ';', value_parser = parse_complex)]
+    pub animate_julia: Option<Vec<Complex>>,
+
+    /// ⏱️ Animation speed
+    ///
+    /// Frames per second of the Julia animation.
+    /// A frame stays on screen until all of its tiles are rendered.
+    #[arg(long, value_name = "FPS")]
+    pub fps: Option<u32>,
+
+    /// 👥 Maximum workers
+    ///
+    /// Limit how many workers 👷 may be connected at once.
+    /// Workers beyond the limit receive an error message and are disconnected.
+    #[arg(long, value_name = "COUNT")]
+    pub max_workers: Option<usize>,
+
+    /// 🧱 Iteration cap
+    ///
+    /// Most iterations per pixel the tasks handed out may ask for 🔁.
+    /// Default is 65536 if not specified.
+    #[arg(long, value_name = "ITERATIONS")]
+    pub max_iteration_cap: Option<u32>,
+
+    /// ⚖️ Worker tile quota
+    ///
+    /// Most tiles a single worker may compute at once 🧩, leaving the rest to the others.
+    /// Unlimited if not specified.
+    #[arg(long, value_name = "TILES")]
+    pub worker_tile_quota: Option<usize>,
+
+    /// 🎚️ Intensity precision
+    ///
+    /// How workers pack the pixel intensities they send back 📦.
+    /// Options: u16, f32, f64. Default is f32 if not specified.
+    #[arg(long, value_name = "FORMAT")]
+    pub intensity_format: Option<IntensityFormat>,
+
+    /// 🔐 TLS certificate
+    ///
+    /// PEM certificate chain presented to the workers, which then connect over TLS.
+    /// Requires --tls-key. Plaintext TCP if not specified.
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    pub tls_cert: Option<std::path::PathBuf>,
+
+    /// 🗝️ TLS private key
+    ///
+    /// PEM private key of the certificate given with --tls-cert.
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<std::path::PathBuf>,
+
+    /// 🧦 UNIX socket
+    ///
+    /// Listen on this UNIX domain socket instead of the address and port 🏠,
+    /// for workers running on the same host.
+    #[arg(long, value_name = "PATH")]
+    pub unix_socket: Option<std::path::PathBuf>,
+
+    /// 📼 Session recording
+    ///
+    /// Write every fragment result received to this file 🗃️, to reproduce a bug with --replay.
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<std::path::PathBuf>,
+
+    /// ⏪ Session replay
+    ///
+    /// Draw the fragment results recorded in this file instead of listening for workers 🔁.
+    /// Run it with the size, fractal and palette of the recording to get the same image.
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    pub replay: Option<std::path::PathBuf>,
+
     /// 🖥️ Server Dashboard
     ///
     /// Enable or disable the server's web dashboard interface 🌐.
@@ -64,3 +175,16 @@ pub struct ServerCommand {
     #[arg(long, value_name = "GRAPHICS")]
     pub graphics: Option<bool>,
 }
+
+fn parse_complex(point: &str) -> Result<Complex, String> {
+    let (re, im) = point
+        .split_once(',')
+        .ok_or_else(|| format!("`{}` is not a `re,im` pair", point))?;
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .map_err(|e| format!("`{}`: {}", value, e))
+    };
+    Ok(Complex::new(parse(re)?, parse(im)?))
+}
@@ -0,0 +1,22 @@
+use clap::Parser;
+
+/// 🩺 Status Command
+///
+/// This command asks a running server's portal for its state 📊.
+#[derive(Parser, Debug)]
+#[command(name = "status", about = "🩺 Show the state of a running server.", long_about = None)]
+pub struct StatusCommand {
+    /// 📡 Portal address
+    ///
+    /// The address of the server's web portal 🌐.
+    /// Default is localhost if not specified.
+    #[arg(short, long, value_name = "ADDRESS")]
+    pub address: Option<String>,
+
+    /// 🔌 Portal port
+    ///
+    /// The port the server's web portal listens on 🎚️.
+    /// Default is 8788 if not specified.
+    #[arg(short, long, value_name = "PORT")]
+    pub port: Option<u16>,
+}
@@ -1,4 +1,5 @@
 use clap::Parser;
+use shared::networking::compression::Compression;
 
 /// 👷 Worker Command
 ///
@@ -10,7 +11,7 @@ pub struct WorkerCommand {
     /// 📛 Worker name
     ///
     /// Unique identifier for the worker 🆔.
-    /// If not set, a random name will be generated.
+    /// If not set, workers are named worker-1, worker-2... and the server suffixes duplicates.
     #[arg(short, long, value_name = "NAME")]
     pub name: Option<String>,
 
@@ -38,4 +39,64 @@ pub struct WorkerCommand {
     /// This could be in terms of tasks, computations, or data size.
     #[arg(long, value_name = "WORKLOAD")]
     pub maximal_work_load: Option<u32>,
+
+    /// 🗜️ Result compression
+    ///
+    /// Compress the pixel data sent back to the server 📦.
+    /// Options: none, gzip, zstd. Default is none if not specified.
+    #[arg(long, value_name = "CODEC")]
+    pub compression: Option<Compression>,
+
+    /// 💤 Task delay
+    ///
+    /// Milliseconds to wait between two tasks 🐢, handy to follow the logs.
+    /// Default is 0 if not specified.
+    #[arg(long, value_name = "MILLISECONDS")]
+    pub task_delay: Option<u64>,
+
+    /// 🗃️ Tile cache
+    ///
+    /// Number of computed tiles kept to answer repeated tasks instantly ♻️.
+    /// Default is 64 if not specified, 0 disables the cache.
+    #[arg(long, value_name = "TILES")]
+    pub cache_capacity: Option<usize>,
+
+    /// 🧱 Iteration ceiling
+    ///
+    /// Tasks asking for more iterations per pixel are lowered to this ceiling 🔁.
+    /// Default is 65536 if not specified.
+    #[arg(long, value_name = "ITERATIONS")]
+    pub max_iterations: Option<u32>,
+
+    /// 🔐 TLS
+    ///
+    /// Connect to the server over TLS, trusting the well-known certificate authorities.
+    #[arg(long)]
+    pub tls: bool,
+
+    /// 📜 TLS certificate authority
+    ///
+    /// PEM certificate the server's certificate must be signed by, implies --tls.
+    #[arg(long, value_name = "PATH")]
+    pub tls_ca: Option<std::path::PathBuf>,
+
+    /// ⚠️ Insecure TLS
+    ///
+    /// Connect over TLS without checking the server's certificate, for testing only.
+    #[arg(long)]
+    pub tls_insecure: bool,
+
+    /// 🧦 UNIX socket
+    ///
+    /// Connect through the UNIX domain socket of a server on the same host 🏠,
+    /// instead of the address and port.
+    #[arg(long, value_name = "PATH")]
+    pub unix_socket: Option<std::path::PathBuf>,
+
+    /// ⏱️ Benchmark
+    ///
+    /// Render a sample fragment locally RUNS times and report the throughput 📈.
+    /// No server is contacted. Default is 10 runs if no value is given.
+    #[arg(long, value_name = "RUNS", num_args = 0..=1, default_missing_value = "10")]
+    pub benchmark: Option<u32>,
 }
@@ -0,0 +1,197 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{de, Deserialize, Deserializer};
+use shared::networking::compression::Compression;
+
+use crate::commands::{server::ServerCommand, worker::WorkerCommand};
+
+/// Config file picked up from the working directory when `--config` is not given.
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IoError(PathBuf, io::Error),
+    ParseError(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::IoError(path, err) => {
+                write!(f, "failed to read {}: {}", path.display(), err)
+            }
+            ConfigError::ParseError(path, err) => {
+                write!(f, "failed to parse {}: {}", path.display(), err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Settings read from a TOML config file, every field is optional.
+///
+/// ```toml
+/// [server]
+/// address = "localhost"
+/// port = 8787
+/// palette = "viridis"
+/// fractal = "mandelbrot"
+///
+/// [worker]
+/// count = 4
+/// compression = "zstd"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerSettings,
+    pub worker: WorkerSettings,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub address: Option<String>,
+    pub port: Option<u16>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub tiles: Option<u32>,
+    pub palette: Option<String>,
+    pub fractal: Option<String>,
+    pub max_workers: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerSettings {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub port: Option<u16>,
+    pub count: Option<u32>,
+    pub maximal_work_load: Option<u32>,
+    #[serde(deserialize_with = "deserialize_compression")]
+    pub compression: Option<Compression>,
+    pub task_delay: Option<u64>,
+    pub cache_capacity: Option<usize>,
+}
+
+fn deserialize_compression<'de, D>(deserializer: D) -> Result<Option<Compression>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|codec| codec.parse().map_err(de::Error::custom))
+        .transpose()
+}
+
+impl Config {
+    pub fn from_path(path: &Path) -> Result<Config, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::IoError(path.to_path_buf(), e))?;
+        toml::from_str(&contents).map_err(|e| ConfigError::ParseError(path.to_path_buf(), e))
+    }
+
+    /// Loads the file passed with `--config`, or `config.toml` if it exists, or the defaults.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        match path {
+            Some(path) => Config::from_path(path),
+            None => {
+                let default_path = Path::new(DEFAULT_CONFIG_PATH);
+                if default_path.exists() {
+                    Config::from_path(default_path)
+                } else {
+                    Ok(Config::default())
+                }
+            }
+        }
+    }
+}
+
+impl ServerSettings {
+    /// Fills the arguments left out on the command line, which take precedence.
+    pub fn apply_to(&self, args: &mut ServerCommand) {
+        args.address = args.address.take().or_else(|| self.address.clone());
+        args.port = args.port.or(self.port);
+        args.width = args.width.or(self.width);
+        args.height = args.height.or(self.height);
+        args.tiles = args.tiles.or(self.tiles);
+        args.max_workers = args.max_workers.or(self.max_workers);
+    }
+}
+
+impl WorkerSettings {
+    /// Fills the arguments left out on the command line, which take precedence.
+    pub fn apply_to(&self, args: &mut WorkerCommand) {
+        args.name = args.name.take().or_else(|| self.name.clone());
+        args.address = args.address.take().or_else(|| self.address.clone());
+        args.port = args.port.or(self.port);
+        args.count = args.count.or(self.count);
+        args.maximal_work_load = args.maximal_work_load.or(self.maximal_work_load);
+        args.compression = args.compression.or(self.compression);
+        args.task_delay = args.task_delay.or(self.task_delay);
+        args.cache_capacity = args.cache_capacity.or(self.cache_capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        [server]
+        address = "0.0.0.0"
+        port = 9000
+        width = 800
+        palette = "viridis"
+        fractal = "julia"
+
+        [worker]
+        count = 4
+        compression = "zstd"
+    "#;
+
+    #[test]
+    fn sample_config_is_loaded_from_a_file() {
+        let path = std::env::temp_dir().join(format!("frakt-config-{}.toml", std::process::id()));
+        fs::write(&path, SAMPLE).unwrap();
+
+        let config = Config::from_path(&path);
+        fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        assert_eq!(config.server.address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(config.server.port, Some(9000));
+        assert_eq!(config.server.width, Some(800));
+        assert_eq!(config.server.height, None);
+        assert_eq!(config.server.palette.as_deref(), Some("viridis"));
+        assert_eq!(config.server.fractal.as_deref(), Some("julia"));
+        assert_eq!(config.worker.count, Some(4));
+        assert_eq!(config.worker.compression, Some(Compression::Zstd));
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        let result = Config::from_path(Path::new("does/not/exist.toml"));
+
+        assert!(matches!(result, Err(ConfigError::IoError(..))));
+    }
+
+    #[test]
+    fn command_line_arguments_override_the_file() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+        let mut args = ServerCommand::parse_from(["server", "--port", "8787"]);
+
+        config.server.apply_to(&mut args);
+
+        assert_eq!(args.port, Some(8787));
+        assert_eq!(args.address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(args.width, Some(800));
+        assert_eq!(args.height, None);
+    }
+}
@@ -1,42 +1,183 @@
 pub mod commands;
+pub mod config;
+pub mod status;
+
+use std::time::Duration;
 
 use clap::Parser;
-use commands::{server::ServerCommand, worker::WorkerCommand, Cli, Commands};
+use commands::{
+    debug_pixel::DebugPixelCommand, render::RenderCommand, server::ServerCommand,
+    status::StatusCommand, worker::WorkerCommand, Cli, Commands, LogFormat,
+};
+use config::{Config, ServerSettings};
+use log::{error, info};
 use shared::{
     env, logger,
-    networking::{server::ServerConfig, worker::Worker},
+    models::{
+        fractal::{fractal_descriptor::FractalDescriptor, mandelbrot::Mandelbrot},
+        fragments::fragment_task::FragmentTask,
+        point::Point,
+        range::Range,
+        resolution::Resolution,
+        u8_data::U8Data,
+    },
+    networking::{
+        server::{Server, ServerConfig},
+        worker::Worker,
+        DEFAULT_MAX_ITERATION, DEFAULT_MAX_ITERATION_CAP, DEFAULT_TILE_CACHE_CAPACITY,
+    },
+    rendering::{color::PaletteHandler, headless},
 };
-use uuid::Uuid;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
     env::init();
-    logger::init_with_level(cli.log_level.to_string().as_str());
+    match cli.log_format {
+        LogFormat::Text => logger::init_with_level(cli.log_level.to_string().as_str()),
+        LogFormat::Json => logger::init_json(cli.log_level.to_string().as_str()),
+    }
+
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     match cli.command {
-        Commands::Worker(args) => run_workers(args).await,
-        Commands::Server(args) => run_server(args).await,
+        Commands::Worker(mut args) => {
+            config.worker.apply_to(&mut args);
+            run_workers(args).await
+        }
+        Commands::Server(mut args) => {
+            config.server.apply_to(&mut args);
+            run_server(args, &config.server).await
+        }
+        Commands::Status(args) => print_status(args).await,
+        Commands::Render(args) => render_locally(args),
+        Commands::DebugPixel(args) => debug_pixel(args),
+    }
+}
+
+fn debug_pixel(args: DebugPixelCommand) {
+    let fractal = args.fractal.unwrap_or_else(|| "mandelbrot".to_string());
+    let max_iterations = args.max_iterations.unwrap_or(DEFAULT_MAX_ITERATION);
+
+    let (render_tx, _render_rx) = tokio::sync::mpsc::channel(1);
+    let mut server = Server::new(
+        ServerConfig::new("localhost".to_string(), 0, 1, 1, 1),
+        render_tx,
+    );
+    if let Err(e) = server.set_fractal_by_name(&fractal) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+    let descriptor = &server.fractals[server.current_fractal];
+
+    let trace = descriptor.generate_trace(max_iterations, args.x, args.y);
+    let (zn, count) = descriptor.generate(max_iterations, args.x, args.y);
+    println!(
+        "{} at ({}, {}), {} of {} iterations",
+        descriptor.name(),
+        args.x,
+        args.y,
+        trace.len(),
+        max_iterations
+    );
+    for (iteration, z) in trace.iter().enumerate() {
+        println!("  {:>5}: {:.6}", iteration + 1, z);
+    }
+    println!("zn: {}, count: {}", zn, count);
+}
+
+fn render_locally(args: RenderCommand) {
+    let width = args.width.unwrap_or(300);
+    let height = args.height.unwrap_or(300);
+    let fractal = args.fractal.unwrap_or_else(|| "mandelbrot".to_string());
+
+    let mut config = ServerConfig::new("localhost".to_string(), 0, width, height, 4);
+    config.palette = args.palette;
+    let named_palette = config.palette.is_some();
+    let (render_tx, _render_rx) = tokio::sync::mpsc::channel(1);
+    let mut server = Server::new(config, render_tx);
+    if let (Some(seed), false) = (args.seed, named_palette) {
+        server.palette = PaletteHandler::with_seed(seed);
+    }
+    if let Err(e) = server.set_fractal_by_name(&fractal) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    info!("Rendering {} at {}x{}", fractal, width, height);
+    let image = headless::render_locally(&mut server);
+    match image.save(&args.out) {
+        Ok(()) => info!("Saved the render to {}", args.out.display()),
+        Err(e) => {
+            error!("Failed to save {}: {}", args.out.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn print_status(args: StatusCommand) {
+    let address = args.address.unwrap_or_else(|| "localhost".to_string());
+    let port = args.port.unwrap_or(status::DEFAULT_PORTAL_PORT);
+
+    match status::fetch_state(&address, port).await {
+        Ok(state) => print!("{}", status::format_status(&state)),
+        Err(e) => {
+            error!("Failed to get the status of {}:{}: {}", address, port, e);
+            std::process::exit(1);
+        }
     }
 }
 
 async fn run_workers(args: WorkerCommand) {
+    if let Some(runs) = args.benchmark {
+        run_benchmark(runs);
+        return;
+    }
+
     let address = args.address.unwrap_or_else(|| "localhost".to_string());
     let port = args.port.unwrap_or(8787);
     let maximal_work_load = args.maximal_work_load.unwrap_or(500);
     let count = args.count.unwrap_or(1);
+    let compression = args.compression.unwrap_or_default();
+    let task_delay = Duration::from_millis(args.task_delay.unwrap_or(0));
+    let cache_capacity = args.cache_capacity.unwrap_or(DEFAULT_TILE_CACHE_CAPACITY);
+    let max_iterations = args.max_iterations.unwrap_or(DEFAULT_MAX_ITERATION_CAP);
+    let tls = args.tls || args.tls_ca.is_some() || args.tls_insecure;
+
+    let shutdown = CancellationToken::new();
+    tokio::spawn(shutdown_on_ctrl_c(shutdown.clone()));
 
     let worker_tasks: Vec<_> = (0..count)
-        .map(|_| {
+        .map(|index| {
             let worker_address = address.clone();
+            // workers sharing a name are told apart by the server, which suffixes duplicates
             let worker_name = args
                 .name
                 .clone()
-                .unwrap_or_else(|| format!("worker-{}", Uuid::new_v4()));
+                .unwrap_or_else(|| format!("worker-{}", index + 1));
+            let shutdown = shutdown.clone();
+            let tls_ca = args.tls_ca.clone();
+            let tls_insecure = args.tls_insecure;
+            let unix_socket = args.unix_socket.clone();
             tokio::spawn(async move {
-                let worker = Worker::new(worker_name, maximal_work_load, worker_address, port);
-                worker::run_worker(worker).await;
+                let mut worker = Worker::new(worker_name, maximal_work_load, worker_address, port);
+                worker.compression = compression;
+                worker.task_delay = task_delay;
+                worker.tile_cache_capacity = cache_capacity;
+                worker.max_iteration_ceiling = max_iterations;
+                worker.tls = tls;
+                worker.tls_ca = tls_ca;
+                worker.tls_insecure = tls_insecure;
+                worker.unix_socket = unix_socket;
+                worker::run_worker(worker, shutdown).await;
             })
         })
         .collect();
@@ -47,13 +188,110 @@ async fn run_workers(args: WorkerCommand) {
     }
 }
 
-async fn run_server(args: ServerCommand) {
+async fn shutdown_on_ctrl_c(shutdown: CancellationToken) {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => {
+            info!("Received Ctrl-C, finishing the tasks in progress before shutting down");
+            shutdown.cancel();
+        }
+        Err(e) => error!("Failed to listen for Ctrl-C: {}", e),
+    }
+}
+
+fn run_benchmark(runs: u32) {
+    let task = FragmentTask::new(
+        U8Data::new(0, 16),
+        FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+        256,
+        Resolution::new(300, 300),
+        Range::new(Point::new(-1.2, -1.2), Point::new(1.2, 1.2)),
+    );
+
+    info!(
+        "Benchmarking {} run(s) of a {:?} fragment",
+        runs, task.resolution
+    );
+    match Worker::benchmark(&task, runs) {
+        Ok(report) => match serde_json::to_string(&report) {
+            Ok(json) => info!("Benchmark report: {}", json),
+            Err(e) => error!("Failed to serialize the benchmark report: {}", e),
+        },
+        Err(e) => error!("Benchmark failed: {}", e),
+    }
+}
+
+async fn run_server(args: ServerCommand, settings: &ServerSettings) {
     let address = args.address.unwrap_or_else(|| "localhost".to_string());
     let port = args.port.unwrap_or(8787);
     let width = args.width.unwrap_or(300);
     let height = args.height.unwrap_or(300);
     let tiles = args.tiles.unwrap_or(4);
 
-    let server_config = ServerConfig::new(address, port, width, height, tiles);
+    let mut server_config = ServerConfig::new(address, port, width, height, tiles);
+    server_config.fractal = settings.fractal.clone();
+    server_config.palette = settings.palette.clone();
+    server_config.render_to = args.render_to;
+    server_config.export_frames = args.export_frames;
+    if let Some(frames) = args.frames {
+        server_config.frames = frames;
+    }
+    server_config.state_file = args.state_file;
+    if let Some(max_workers) = args.max_workers {
+        server_config.max_workers = max_workers;
+    }
+    if let Some(max_iteration_cap) = args.max_iteration_cap {
+        server_config.max_iteration_cap = max_iteration_cap;
+    }
+    server_config.worker_tile_quota = args.worker_tile_quota;
+    server_config.julia_path = args.animate_julia;
+    if let Some(fps) = args.fps {
+        server_config.animation_fps = fps;
+    }
+    if let Some(intensity_format) = args.intensity_format {
+        server_config.intensity_format = intensity_format;
+    }
+    server_config.tls_cert = args.tls_cert;
+    server_config.tls_key = args.tls_key;
+    server_config.unix_socket = args.unix_socket;
+    server_config.record_session = args.record;
+    server_config.replay = args.replay;
+
+    if args.plan {
+        print_plan(server_config);
+        return;
+    }
     server::run_graphics_server(&server_config).await;
 }
+
+fn print_plan(config: ServerConfig) {
+    let (render_tx, _render_rx) = tokio::sync::mpsc::channel(1);
+    let mut server = Server::new(config, render_tx);
+    if let Some(path) = server
+        .config
+        .state_file
+        .clone()
+        .filter(|path| path.exists())
+    {
+        if let Err(e) = server.load_state(&path) {
+            error!("Failed to load the view state: {}", e);
+        }
+    }
+
+    let plan = server.plan_tiles();
+    println!(
+        "{} tiles for a {}x{} canvas",
+        plan.len(),
+        server.config.width,
+        server.config.height
+    );
+    for (index, range) in plan.iter().enumerate() {
+        println!(
+            "  tile {}: ({}, {}) -> ({}, {})",
+            index, range.min.x, range.min.y, range.max.x, range.max.y
+        );
+    }
+    println!(
+        "covering ({}, {}) -> ({}, {})",
+        server.range.min.x, server.range.min.y, server.range.max.x, server.range.max.y
+    );
+}
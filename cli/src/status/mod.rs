@@ -0,0 +1,166 @@
+use std::{fmt, fmt::Write as _, io};
+
+use shared::dtos::server_dto::ServerDto;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Port of the server's web portal when `--port` is not given.
+pub const DEFAULT_PORTAL_PORT: u16 = 8788;
+
+#[derive(Debug)]
+pub enum StatusError {
+    IoError(io::Error),
+    HttpError(String),
+    ParseError(serde_json::Error),
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusError::IoError(err) => write!(f, "failed to reach the portal: {}", err),
+            StatusError::HttpError(status) => write!(f, "the portal answered {}", status),
+            StatusError::ParseError(err) => write!(f, "unexpected server state: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// Fetches the server state from the portal's `/state` endpoint.
+pub async fn fetch_state(address: &str, port: u16) -> Result<ServerDto, StatusError> {
+    let mut stream = TcpStream::connect((address, port))
+        .await
+        .map_err(StatusError::IoError)?;
+
+    // HTTP/1.0 keeps the body in one piece, without chunked encoding
+    let request = format!("GET /state HTTP/1.0\r\nHost: {}:{}\r\n\r\n", address, port);
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(StatusError::IoError)?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .await
+        .map_err(StatusError::IoError)?;
+    let response = String::from_utf8_lossy(&response);
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| StatusError::HttpError("a truncated response".to_string()))?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(StatusError::HttpError(status.to_string()));
+    }
+
+    serde_json::from_str(body).map_err(StatusError::ParseError)
+}
+
+/// Lays the state out as a two column table, followed by one line per worker.
+pub fn format_status(state: &ServerDto) -> String {
+    let mut output = String::new();
+    let range = &state.range;
+    let rows = [
+        ("Fractal", state.fractal.name().to_string()),
+        ("Palette", state.palette.clone()),
+        (
+            "Range",
+            format!(
+                "({}, {}) -> ({}, {})",
+                range.min.x, range.min.y, range.max.x, range.max.y
+            ),
+        ),
+        ("Queued tasks", state.queued_tasks.to_string()),
+        ("In-flight tasks", state.inflight_tasks.to_string()),
+        ("Remaining tiles", state.remaining_tiles.to_string()),
+        ("Dropped frags", state.dropped_fragments.to_string()),
+        ("Workers", state.worker_statuses.len().to_string()),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(output, "{:<16} {}", label, value);
+    }
+
+    let mut workers: Vec<_> = state.worker_statuses.iter().collect();
+    workers.sort_by(|a, b| a.name.cmp(&b.name));
+    for worker in workers {
+        let _ = writeln!(
+            output,
+            "  {:<20} {:<22} load {:<6} seen {:.1}s ago{}",
+            worker.name,
+            worker.address,
+            worker.maximal_work_load,
+            worker.seconds_since_last_seen,
+            if worker.stale { " (stale)" } else { "" }
+        );
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{
+        dtos::{rendering_data::RenderingData, worker_status::WorkerStatus},
+        networking::server::{Server, ServerConfig},
+    };
+    use tokio::{net::TcpListener, sync::mpsc};
+
+    use super::*;
+
+    fn canned_state() -> ServerDto {
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(1);
+        let config = ServerConfig::new("localhost".to_string(), 8787, 300, 300, 2);
+        let mut state = Server::new(config, render_tx).to_dto();
+        state.worker_statuses.push(WorkerStatus {
+            address: "127.0.0.1:4000".to_string(),
+            name: "worker-1".to_string(),
+            maximal_work_load: 500,
+            seconds_since_last_seen: 1.5,
+            stale: false,
+        });
+        state
+    }
+
+    async fn stub_portal(response: String) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let read = socket.read(&mut request).await.unwrap();
+            assert!(String::from_utf8_lossy(&request[..read]).starts_with("GET /state "));
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn the_state_served_by_the_portal_is_printed() {
+        let body = serde_json::to_string(&canned_state()).unwrap();
+        let port = stub_portal(format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+            body
+        ))
+        .await;
+
+        let state = fetch_state("127.0.0.1", port).await.unwrap();
+        let table = format_status(&state);
+
+        assert!(table.contains("Fractal          mandelbrot"));
+        assert!(table.contains("Queued tasks     0"));
+        assert!(table.contains("Range            (-1.2, -1.2) -> (1.2, 1.2)"));
+        assert!(table.contains("worker-1"));
+    }
+
+    #[tokio::test]
+    async fn error_statuses_are_reported() {
+        let port = stub_portal("HTTP/1.1 404 Not Found\r\n\r\n".to_string()).await;
+
+        let result = fetch_state("127.0.0.1", port).await;
+
+        assert!(matches!(result, Err(StatusError::HttpError(status)) if status.contains("404")));
+    }
+}
@@ -1,8 +1,7 @@
-use std::f64::consts::PI;
+use core::f64::consts::PI;
 
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Complex {
     pub re: f64,
     pub im: f64,
@@ -18,17 +17,62 @@ impl Complex {
     }
 
     pub fn arg(&self) -> f64 {
-        (self.im.atan2(self.re) / (2.0 * PI)).rem_euclid(1.0)
+        let turns = math::atan2(self.im, self.re) / (2.0 * PI) % 1.0;
+        if turns < 0.0 {
+            turns + 1.0
+        } else {
+            turns
+        }
+    }
+
+    /// Raises to an integer power by squaring, negative powers invert the result.
+    pub fn powi(self, n: i32) -> Self {
+        let mut base = self;
+        let mut exponent = n.unsigned_abs();
+        let mut result = Complex::new(1.0, 0.0);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+
+        if n < 0 {
+            Complex::new(1.0, 0.0) / result
+        } else {
+            result
+        }
     }
 
     pub fn sin(self) -> Self {
-        let re = self.re.sin() * self.im.cosh();
-        let im = self.re.cos() * self.im.sinh();
+        let re = math::sin(self.re) * math::cosh(self.im);
+        let im = math::cos(self.re) * math::sinh(self.im);
         Self { re, im }
     }
 }
 
-impl std::ops::Add for Complex {
+/// Prints `a + bi` or `a - bi`, a precision such as `{:.3}` applies to both parts.
+impl core::fmt::Display for Complex {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (sign, im) = if self.im < 0.0 {
+            ('-', -self.im)
+        } else {
+            // adding zero turns a negative zero positive
+            ('+', self.im + 0.0)
+        };
+        match f.precision() {
+            Some(precision) => write!(
+                f,
+                "{:.*} {} {:.*}i",
+                precision, self.re, sign, precision, im
+            ),
+            None => write!(f, "{} {} {}i", self.re, sign, im),
+        }
+    }
+}
+
+impl core::ops::Add for Complex {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
@@ -39,7 +83,7 @@ impl std::ops::Add for Complex {
     }
 }
 
-impl std::ops::Sub for Complex {
+impl core::ops::Sub for Complex {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
@@ -50,7 +94,7 @@ impl std::ops::Sub for Complex {
     }
 }
 
-impl std::ops::Mul for Complex {
+impl core::ops::Mul for Complex {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self {
@@ -61,7 +105,7 @@ impl std::ops::Mul for Complex {
     }
 }
 
-impl std::ops::Div for Complex {
+impl core::ops::Div for Complex {
     type Output = Self;
 
     fn div(self, rhs: Self) -> Self {
@@ -72,3 +116,110 @@ impl std::ops::Div for Complex {
         }
     }
 }
+
+// the float methods live in std, libm stands in for them without it
+#[cfg(feature = "std")]
+mod math {
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        y.atan2(x)
+    }
+
+    pub fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+
+    pub fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+
+    pub fn sinh(x: f64) -> f64 {
+        x.sinh()
+    }
+
+    pub fn cosh(x: f64) -> f64 {
+        x.cosh()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod math {
+    pub use libm::{atan2, cos, cosh, sin, sinh};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{format, string::ToString};
+
+    use super::*;
+
+    fn assert_close(actual: Complex, expected: Complex) {
+        assert!(
+            (actual - expected).arg_sq() < 1e-18,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn powi_matches_repeated_multiplication() {
+        let z = Complex::new(0.6, -1.3);
+
+        assert_close(z.powi(0), Complex::new(1.0, 0.0));
+        assert_close(z.powi(1), z);
+        assert_close(z.powi(5), z * z * z * z * z);
+        assert_close(z.powi(-2), Complex::new(1.0, 0.0) / (z * z));
+    }
+
+    // runs against libm with `--no-default-features`
+    #[test]
+    fn the_transcendental_functions_hold_with_or_without_std() {
+        assert_close(Complex::new(0.0, 0.0).sin(), Complex::new(0.0, 0.0));
+        assert_close(
+            Complex::new(PI / 2.0, 1.0).sin(),
+            Complex::new(1.543_080_634_815_243_7, 0.0),
+        );
+        assert_close(
+            Complex::new(0.0, 1.0).sin(),
+            Complex::new(0.0, 1.175_201_193_643_801_4),
+        );
+
+        assert!(Complex::new(1.0, 0.0).arg().abs() < 1e-12);
+        assert!((Complex::new(0.0, 1.0).arg() - 0.25).abs() < 1e-12);
+        assert!((Complex::new(0.0, -1.0).arg() - 0.75).abs() < 1e-12);
+        assert!((Complex::new(-1.0, -1e-300).arg() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn a_positive_imaginary_part_is_added() {
+        let z = Complex::new(1.5, 2.0);
+
+        assert_eq!(z.to_string(), "1.5 + 2i");
+        assert_eq!(format!("{:.3}", z), "1.500 + 2.000i");
+    }
+
+    #[test]
+    fn a_negative_imaginary_part_is_subtracted() {
+        let z = Complex::new(-0.25, -1.0 / 3.0);
+
+        assert_eq!(format!("{:.2}", z), "-0.25 - 0.33i");
+        assert_eq!(Complex::new(1.0, -2.0).to_string(), "1 - 2i");
+    }
+
+    #[test]
+    fn a_zero_imaginary_part_is_still_printed() {
+        assert_eq!(Complex::new(3.0, 0.0).to_string(), "3 + 0i");
+        assert_eq!(Complex::new(3.0, -0.0).to_string(), "3 + 0i");
+        assert_eq!(format!("{:.1}", Complex::new(0.0, 0.0)), "0.0 + 0.0i");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn complex_numbers_round_trip_through_json() {
+        let z = Complex::new(0.25, -1.5);
+
+        let parsed: Complex = serde_json::from_str(&serde_json::to_string(&z).unwrap()).unwrap();
+
+        assert_close(parsed, z);
+    }
+}
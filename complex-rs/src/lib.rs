@@ -1,3 +1,8 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
 pub mod complex;
 
 pub fn add(left: usize, right: usize) -> usize {
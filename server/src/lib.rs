@@ -1,33 +1,52 @@
 use std::{
-    mem::size_of,
+    future::Future,
+    io::ErrorKind,
     net::SocketAddr,
+    path::Path,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 
 use shared::{
     dtos::rendering_data::RenderingData,
+    env,
     models::{
         fragments::{
-            fragment::Fragment, fragment_request::FragmentRequest, fragment_result::FragmentResult,
-            fragment_task::FragmentTask,
+            error_message::ErrorMessage, fragment::Fragment, fragment_request::FragmentRequest,
+            fragment_result::FragmentResult, fragment_task::FragmentTask, heartbeat::Heartbeat,
         },
-        pixel::pixel_intensity::PixelIntensity,
+        pixel::pixel_intensity::IntensityFormat,
     },
     networking::{
-        read_message_raw,
+        compression::Compression,
+        error::NetworkingError,
+        read_frame_with_limit,
         result::NetworkingResult,
         send_message,
         server::{Server, ServerConfig},
+        session::{read_session, RecordedResult, SessionRecorder},
+        signature::{sign_payload, verify_payload, SIGNATURE_SIZE},
+        tls::{self, TlsAcceptor},
+        transport::Listener,
         worker::Worker,
     },
-    rendering::launch_graphics_engine,
+    rendering::{
+        error::GraphicsError,
+        headless::{export_frames, render_to_file},
+        launch_graphics_engine,
+    },
 };
 use tokio::{
-    net::{TcpListener, TcpStream},
-    sync::mpsc::{self, Sender},
+    io::{AsyncRead, AsyncWrite},
+    sync::{
+        mpsc::{self, error::TrySendError, Sender},
+        Semaphore,
+    },
+    task::JoinHandle,
 };
+use tokio_util::sync::CancellationToken;
 
 pub async fn run_graphics_server(config: &ServerConfig) {
     match execute_server(config).await {
@@ -37,19 +56,98 @@ pub async fn run_graphics_server(config: &ServerConfig) {
 }
 
 async fn execute_server(config: &ServerConfig) -> NetworkingResult<()> {
-    let server_address = format!("{}:{}", config.address, config.port);
-    let listener = initialize_server(&server_address).await?;
-    info!("Server is listening on {}", server_address);
+    if let Some(path) = &config.replay {
+        return replay(config, path).await;
+    }
+    if env::secret().is_empty() {
+        warn!(
+            "{} is not set, the messages are signed with an empty key anyone can forge",
+            env::SECRET_ENV_VAR
+        );
+    }
 
-    let (render_tx, render_rx) = mpsc::channel::<RenderingData>(32);
+    let transport = config.transport();
+    let listener = transport.bind().await?;
+    let tls = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => Some(tls::acceptor(cert, key)?),
+        (None, None) => None,
+        _ => {
+            return Err(NetworkingError::Tls(
+                "a TLS certificate and its private key go together".to_string(),
+            ))
+        }
+    };
+    info!(
+        "Server is listening on {}{}",
+        transport,
+        if tls.is_some() { " over TLS" } else { "" }
+    );
+
+    let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
     let server = create_server(config, &render_tx);
+    restore_state(&server);
+    if let Some(path) = &config.record_session {
+        server.lock().unwrap().recorder = Some(Arc::new(SessionRecorder::create(path)?));
+        info!("Recording the fragment results to {}", path.display());
+    }
+
+    let shutdown = CancellationToken::new();
+    tokio::spawn(shutdown_on_ctrl_c(shutdown.clone()));
+
+    // once the connections are drained the window is closed as well
+    let drained = CancellationToken::new();
+    let connection_handler = tokio::spawn({
+        let server = server.clone();
+        let shutdown = shutdown.clone();
+        let drained = drained.clone();
+        async move {
+            handle_connections(listener, tls, server.clone(), render_tx.clone(), shutdown).await;
+            persist_state(&server);
+            drained.cancel();
+        }
+    });
+    tokio::spawn(reclaim_stale_tasks(server.clone()));
+    if let Some(path) = config.julia_path.clone() {
+        server
+            .lock()
+            .unwrap()
+            .animate_julia(path, config.animation_fps);
+        // exported frames move on once written, not on a timer
+        if config.export_frames.is_none() {
+            tokio::spawn(animate(server.clone()));
+        }
+    }
+
+    if let Some(directory) = &config.export_frames {
+        let frames = config.frames;
+        let exported = tokio::select! {
+            exported = export_frames(server.clone(), &mut render_rx, directory, frames) => {
+                exported.map(|_| ())
+            }
+            _ = shutdown.cancelled() => {
+                info!("Frame export interrupted.");
+                Ok(())
+            }
+        };
+        shutdown.cancel();
+        let _ = connection_handler.await;
+        return exported.map_err(|e| NetworkingError::Error(Box::new(e)));
+    }
 
-    let connection_handler = tokio::spawn(handle_connections(
-        listener,
-        server.clone(),
-        render_tx.clone(),
-    ));
-    let graphics_handler = launch_graphics_engine(server.clone(), render_rx);
+    if let Some(path) = &config.render_to {
+        let rendered = tokio::select! {
+            rendered = render_to_file(server.clone(), &mut render_rx, path) => rendered,
+            _ = shutdown.cancelled() => {
+                info!("Headless rendering interrupted.");
+                Ok(())
+            }
+        };
+        shutdown.cancel();
+        let _ = connection_handler.await;
+        return rendered.map_err(|e| NetworkingError::Error(Box::new(e)));
+    }
+
+    let graphics_handler = launch_graphics_engine(server.clone(), render_rx, drained);
 
     // tokio::spawn(async move {
     //     loop {
@@ -61,75 +159,390 @@ async fn execute_server(config: &ServerConfig) -> NetworkingResult<()> {
     //     }
     // });
 
-    let _ = tokio::join!(connection_handler, graphics_handler);
+    serve_with_graphics(connection_handler, graphics_handler, shutdown).await;
 
     Ok(())
 }
 
+// the window only shows the work, the workers are still served when it cannot be opened, and
+// closing it shuts the server down
+async fn serve_with_graphics(
+    connection_handler: JoinHandle<()>,
+    graphics: impl Future<Output = Result<(), GraphicsError>>,
+    shutdown: CancellationToken,
+) {
+    match graphics.await {
+        Ok(()) => {
+            info!("Window closed, draining the connections before shutting down.");
+            shutdown.cancel();
+        }
+        Err(e) => warn!("Continuing without graphics: {}", e),
+    }
+    let _ = connection_handler.await;
+}
+
+// draws a recorded session without any worker, to the window or to `render_to`
+async fn replay(config: &ServerConfig, path: &Path) -> NetworkingResult<()> {
+    let session = read_session(path).await?;
+    info!(
+        "Replaying {} fragment result(s) from {}",
+        session.len(),
+        path.display()
+    );
+
+    // the server keeps a sender of its own, it would hold the channel open past the session
+    let (unused_tx, _) = mpsc::channel::<RenderingData>(1);
+    let server = create_server(config, &unused_tx);
+    let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+    tokio::spawn(replay_session(session, server.clone(), render_tx));
+
+    match &config.render_to {
+        Some(path) => render_to_file(server, &mut render_rx, path)
+            .await
+            .map_err(|e| NetworkingError::Error(Box::new(e))),
+        // with no window there is nowhere to replay to
+        None => launch_graphics_engine(server, render_rx, CancellationToken::new())
+            .await
+            .map_err(|e| NetworkingError::Error(Box::new(e))),
+    }
+}
+
+/// Feeds recorded results through [`process_fragment_result`] as if their workers had just
+/// sent them, each answering the task it was recorded with.
+async fn replay_session(
+    session: Vec<RecordedResult>,
+    server: Arc<Mutex<Server>>,
+    render_tx: Sender<RenderingData>,
+) {
+    let replay_addr = SocketAddr::from(([0, 0, 0, 0], 0));
+    for recorded in session {
+        let result = match FragmentResult::from_json(&recorded.json_message) {
+            Ok(result) => result,
+            Err(e) => {
+                warn!(
+                    "Skipping a recorded FragmentResult that does not parse: {}",
+                    e
+                );
+                continue;
+            }
+        };
+
+        // nothing is dropped for the window falling behind, a replay waits for it instead
+        if render_tx.reserve().await.is_err() {
+            return;
+        }
+        server
+            .lock()
+            .unwrap()
+            .mark_task_inflight(replay_addr, recorded.task);
+        process_fragment_result(
+            result,
+            &recorded.json_message,
+            &recorded.data,
+            render_tx.clone(),
+            replay_addr,
+            server.clone(),
+        )
+        .await;
+    }
+    info!("Replayed the whole session.");
+}
+
+async fn reclaim_stale_tasks(server: Arc<Mutex<Server>>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        let reclaimed = {
+            let mut server = server.lock().unwrap();
+            let timeout = server.config.task_timeout;
+            server.reclaim_stale_tasks(timeout)
+        };
+        if reclaimed > 0 {
+            warn!("Reclaimed {} stale fragment task(s)", reclaimed);
+        }
+    }
+}
+
+// a frame is shown for at least its interval, and until all of its tiles came back
+async fn animate(server: Arc<Mutex<Server>>) {
+    loop {
+        let Some(frame_interval) = server
+            .lock()
+            .unwrap()
+            .animation
+            .as_ref()
+            .map(|animation| animation.frame_interval)
+        else {
+            return;
+        };
+        tokio::time::sleep(frame_interval).await;
+
+        while !server.lock().unwrap().frame_complete() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        if !server.lock().unwrap().step_animation() {
+            return;
+        }
+    }
+}
+
+async fn shutdown_on_ctrl_c(shutdown: CancellationToken) {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => {
+            info!("Received Ctrl-C, draining the connections before shutting down.");
+            shutdown.cancel();
+        }
+        Err(e) => error!("Failed to listen for Ctrl-C: {}", e),
+    }
+}
+
+// the queue is rebuilt from the view on startup, so saving the view is enough to resume it
+fn persist_state(server: &Arc<Mutex<Server>>) {
+    let server = server.lock().unwrap();
+    if let Some(path) = &server.config.state_file {
+        match server.save_state(path) {
+            Ok(()) => info!("Saved the view state to {}", path.display()),
+            Err(e) => error!("Failed to save the view state: {}", e),
+        }
+    }
+}
+
+fn restore_state(server: &Arc<Mutex<Server>>) {
+    let mut server = server.lock().unwrap();
+    let Some(path) = server.config.state_file.clone() else {
+        return;
+    };
+    if !path.exists() {
+        debug!("No view state to restore at {}", path.display());
+        return;
+    }
+
+    match server.load_state(&path) {
+        Ok(()) => info!("Restored the view state from {}", path.display()),
+        Err(e) => warn!("Failed to restore the view state: {}", e),
+    }
+}
+
 fn create_server(config: &ServerConfig, render_tx: &Sender<RenderingData>) -> Arc<Mutex<Server>> {
     let server = Server::new(config.clone(), render_tx.clone());
     Arc::new(Mutex::new(server))
 }
 
-async fn initialize_server(address: &str) -> NetworkingResult<TcpListener> {
-    TcpListener::bind(address).await.map_err(Into::into)
-}
-
 async fn handle_connections(
-    listener: TcpListener,
+    mut listener: Listener,
+    tls: Option<TlsAcceptor>,
     server: Arc<Mutex<Server>>,
     render_tx: Sender<RenderingData>,
+    shutdown: CancellationToken,
 ) {
     info!("Starting to handle incoming connections.");
-    while let Ok((socket, socket_addr)) = listener.accept().await {
+    let (max_workers, drain_timeout, read_timeout) = {
+        let server = server.lock().unwrap();
+        (
+            server.config.max_workers,
+            server.config.drain_timeout,
+            server.config.read_timeout,
+        )
+    };
+    let connection_slots = Arc::new(Semaphore::new(max_workers));
+
+    loop {
+        let (socket, socket_addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("Failed to accept a connection: {}", e);
+                    break;
+                }
+            },
+            _ = shutdown.cancelled() => break,
+        };
+
+        let Ok(slot) = connection_slots.clone().try_acquire_owned() else {
+            warn!(
+                "Rejecting {}, already serving {} workers.",
+                socket_addr, max_workers
+            );
+            match tls.clone() {
+                // a peer stalling its handshake is given up on like one stalling a read
+                Some(tls) => tokio::spawn(async move {
+                    match tokio::time::timeout(read_timeout, tls.accept(socket)).await {
+                        Ok(Ok(socket)) => reject_connection(socket, max_workers).await,
+                        Ok(Err(e)) => debug!("TLS handshake with {} failed: {}", socket_addr, e),
+                        Err(_) => debug!("TLS handshake with {} timed out", socket_addr),
+                    }
+                }),
+                None => tokio::spawn(reject_connection(socket, max_workers)),
+            };
+            continue;
+        };
+
         debug!("Accepted new connection.");
         let tx_clone = render_tx.clone();
-        tokio::spawn(handle_connection(
-            socket,
-            socket_addr,
-            server.clone(),
-            tx_clone,
-        ));
+        let server = server.clone();
+        let shutdown = shutdown.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            match tls {
+                Some(tls) => match tokio::time::timeout(read_timeout, tls.accept(socket)).await {
+                    Ok(Ok(socket)) => {
+                        handle_connection(socket, socket_addr, server, tx_clone, shutdown).await
+                    }
+                    Ok(Err(e)) => warn!("TLS handshake with {} failed: {}", socket_addr, e),
+                    Err(_) => warn!("TLS handshake with {} timed out", socket_addr),
+                },
+                None => handle_connection(socket, socket_addr, server, tx_clone, shutdown).await,
+            }
+            drop(slot);
+        });
+    }
+
+    // every slot coming back means every connection is done
+    drop(listener);
+    info!("No longer accepting connections, draining the open ones.");
+    let all_slots = connection_slots.acquire_many(max_workers as u32);
+    if tokio::time::timeout(drain_timeout, all_slots)
+        .await
+        .is_err()
+    {
+        warn!(
+            "Connections still open after {:?}, shutting down anyway.",
+            drain_timeout
+        );
     }
 }
 
-async fn handle_connection(
-    mut socket: TcpStream,
+async fn reject_connection<S: AsyncWrite + Unpin>(mut socket: S, max_workers: usize) {
+    let reason = format!(
+        "the server is at capacity, it serves at most {} workers",
+        max_workers
+    );
+    if let Err(e) = send_error(&mut socket, reason).await {
+        debug!("Failed to send the rejection: {}", e);
+    }
+}
+
+async fn send_error<S: AsyncWrite + Unpin>(
+    socket: &mut S,
+    message: String,
+) -> NetworkingResult<()> {
+    let json = ErrorMessage::new(message).to_json()?;
+    send_message(socket, json.to_string().as_bytes(), None, Compression::None).await
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+    mut socket: S,
     socket_addr: SocketAddr,
     server: Arc<Mutex<Server>>,
     render_tx: Sender<RenderingData>,
+    shutdown: CancellationToken,
 ) {
     debug!("Initiating connection handling.");
-    let raw_message = match read_message_raw(&mut socket).await {
-        Ok(msg) => {
-            trace!("Received raw message: {:?}", msg);
-            msg
+    let (max_message_size, read_timeout) = {
+        let server = server.lock().unwrap();
+        (server.config.max_message_size, server.config.read_timeout)
+    };
+
+    // A worker keeps its connection open and cycles through request -> task -> result
+    loop {
+        // on shutdown, a worker busy with a task is still waited for so its result is not lost
+        let busy = server
+            .lock()
+            .unwrap()
+            .inflight_tasks
+            .contains_key(&socket_addr);
+        let message = tokio::select! {
+            biased;
+            _ = shutdown.cancelled(), if !busy => {
+                debug!("Closing the connection to {} on shutdown.", socket_addr);
+                break;
+            }
+            message = read_frame_with_limit(&mut socket, max_message_size, read_timeout) => {
+                message.map_err(|e| match e {
+                    NetworkingError::Io(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+                    e => Some(e.to_string()),
+                })
+            }
+        };
+        let frame = match message {
+            Ok(msg) => msg,
+            Err(None) => {
+                debug!("Connection closed by {}.", socket_addr);
+                break;
+            }
+            Err(Some(e)) => {
+                error!("Failed to read message: {}", e);
+                break;
+            }
+        };
+        trace!("Frame: {:?}", frame);
+        let json_message = frame.json_message();
+        server.lock().unwrap().record_activity(&socket_addr);
+
+        if let Ok(fragment_result) = FragmentResult::from_json(&json_message) {
+            debug!("Processing FragmentResult.");
+            process_fragment_result(
+                fragment_result,
+                &json_message,
+                frame.data(),
+                render_tx.clone(),
+                socket_addr,
+                server.clone(),
+            )
+            .await;
+        } else if let Ok(request) = FragmentRequest::from_json(&json_message) {
+            if shutdown.is_cancelled() {
+                debug!("Not handing out a task to {}, shutting down.", socket_addr);
+                break;
+            }
+            debug!("Processing FragmentRequest.");
+            if !process_fragment_request(request, server.clone(), &mut socket, socket_addr).await {
+                break;
+            }
+        } else if let Ok(heartbeat) = Heartbeat::from_json(&json_message) {
+            trace!("Heartbeat from {}.", heartbeat.worker_name);
+        } else {
+            let reason = decoding_error(&json_message).to_string();
+            warn!("Unrecognized message from {}: {}", socket_addr, reason);
+            if let Err(e) = send_error(&mut socket, reason).await {
+                error!("Failed to send the error to {}: {}", socket_addr, e);
+                break;
+            }
         }
-        Err(e) => {
-            error!("Failed to read message: {:?}", e);
-            return;
+    }
+
+    let mut server = server.lock().unwrap();
+    server.unregister_worker(&socket_addr);
+    if server.reclaim_task(&socket_addr) {
+        warn!(
+            "Worker at {} left with a task in flight, task reclaimed.",
+            socket_addr
+        );
+    }
+}
+
+// explains why a message matched none of the fragments, through the one it claims to be
+fn decoding_error(json_message: &str) -> NetworkingError {
+    let error = match serde_json::from_str::<serde_json::Value>(json_message) {
+        Ok(value) if value.get("FragmentResult").is_some() => {
+            FragmentResult::from_json(json_message).err()
+        }
+        Ok(value) if value.get("FragmentRequest").is_some() => {
+            FragmentRequest::from_json(json_message).err()
         }
+        Ok(_) => None,
+        Err(e) => Some(e),
     };
-    trace!("Raw message: {:?}", raw_message);
 
-    if let Ok(fragment_result) = FragmentResult::from_json(&raw_message.json_message) {
-        debug!("Processing FragmentResult.");
-        process_fragment_result(
-            fragment_result,
-            &raw_message.data,
-            render_tx,
-            socket_addr,
-            server,
-        )
-        .await;
-    } else if let Ok(request) = FragmentRequest::from_json(&raw_message.json_message) {
-        debug!("Processing FragmentRequest.");
-        process_fragment_request(request, server.clone(), &mut socket, socket_addr).await;
+    match error {
+        Some(e) => NetworkingError::serde(e, json_message),
+        None => NetworkingError::MalformedFrame("unknown message type".to_string()),
     }
 }
 
 async fn process_fragment_result(
     result: FragmentResult,
+    json_message: &str,
     data: &[u8],
     render_tx: Sender<RenderingData>,
     socket_addr: SocketAddr,
@@ -138,54 +551,103 @@ async fn process_fragment_result(
     info!("Processing received FragmentResult.");
     trace!("FragmentResult details: {:?}", result);
 
-    // Skip the first 16 bytes of the data
-    let data = &data[16..];
-    if data.len() % size_of::<PixelIntensity>() != 0 {
-        error!("Data size is not aligned with PixelIntensity size.");
+    // the view changed since the task was handed out, the fragment would be drawn out of place
+    let (task, recorder) = {
+        let mut server = server.lock().unwrap();
+        let Some(task) = server.complete_task(&result) else {
+            debug!("Dropping a FragmentResult that matches no pending task.");
+            return;
+        };
+        (task, server.recorder.clone())
+    };
+    if !result.matches_task(&task) {
+        error!("Rejecting a FragmentResult inconsistent with its task, task requeued.");
+        server.lock().unwrap().enqueue_task(task);
         return;
     }
+    let intensity_format = task.intensity_format;
 
-    let pixel_intensities: Vec<PixelIntensity> = data
-        .chunks_exact(size_of::<PixelIntensity>())
-        .filter_map(|chunk| {
-            let zn_bytes = chunk.get(0..4)?.try_into().ok()?;
-            let count_bytes = chunk.get(4..8)?.try_into().ok()?;
-            Some(PixelIntensity {
-                zn: f32::from_be_bytes(zn_bytes),
-                count: f32::from_be_bytes(count_bytes),
-            })
-        })
-        .collect();
+    // The first bytes of the data hold the signature of the rest of the payload. The task was
+    // taken off the in-flight ones, a rejected result has to hand it back to be computed again
+    if data.len() < SIGNATURE_SIZE {
+        error!("FragmentResult data is too short to hold a signature, task requeued.");
+        server.lock().unwrap().enqueue_task(task);
+        return;
+    }
+    let signed_data = data;
+    let (signature, data) = data.split_at(SIGNATURE_SIZE);
+    if !verify_payload(env::secret(), json_message.as_bytes(), data, signature) {
+        error!("Rejecting FragmentResult with an invalid signature, task requeued.");
+        server.lock().unwrap().enqueue_task(task);
+        return;
+    }
+
+    if !result.verify_checksum(data) {
+        error!("Rejecting FragmentResult whose data fails its checksum, task requeued.");
+        server.lock().unwrap().enqueue_task(task);
+        return;
+    }
+
+    let pixel_count = result.resolution.pixel_count();
+    if data.len() != pixel_count * intensity_format.pixel_size() {
+        error!(
+            "Data holds {} bytes, expected {} {} pixel intensities, task requeued.",
+            data.len(),
+            pixel_count,
+            intensity_format
+        );
+        server.lock().unwrap().enqueue_task(task);
+        return;
+    }
+
+    // only results that would be drawn are recorded, a replay waits for every one of them
+    if let Some(recorder) = recorder {
+        if let Err(e) = recorder.record(&task, json_message, signed_data).await {
+            error!("Failed to record the FragmentResult: {}", e);
+        }
+    }
 
     //NOTE: we currenlty only care about the count
-    let iterations: Vec<f64> = pixel_intensities.iter().map(|pi| pi.count as f64).collect();
+    let iterations = intensity_format.counts(data);
 
-    let worker = {
-        let server = server.lock().unwrap();
-        if let Some(worker) = server.get_worker(&socket_addr) {
+    let (worker, palette) = {
+        let mut server = server.lock().unwrap();
+        let worker = if let Some(worker) = server.get_worker(&socket_addr) {
             worker.name.to_string()
         } else {
             "[unknown worker]".to_string()
+        };
+        server.record_completed_fragment(&worker, iterations.len() as u64);
+        server.histogram.record(&iterations);
+        if let Some(compute_ms) = result.compute_ms {
+            server.record_tile_timing(result.range, compute_ms);
         }
+        (worker, server.palette.clone())
     };
 
-    let rendering_data = RenderingData {
-        result,
-        iterations,
-        worker,
-    };
+    let rendering_data = RenderingData::from_result(result, worker, iterations, &palette);
 
-    if let Err(e) = render_tx.send(rendering_data).await {
-        error!("Failed to send rendering data: {}", e);
+    // waiting for the window would stall the intake of this connection, the tile is computed
+    // again later instead
+    match render_tx.try_send(rendering_data) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            warn!("Rendering is falling behind, dropped a fragment and requeued its task.");
+            let mut server = server.lock().unwrap();
+            server.dropped_fragments += 1;
+            server.enqueue_task(task);
+        }
+        // nothing draws the fragments when running without graphics
+        Err(TrySendError::Closed(_)) => debug!("Rendering data dropped, channel closed"),
     }
 }
 
-async fn process_fragment_request(
+async fn process_fragment_request<S: AsyncWrite + Unpin>(
     request: FragmentRequest,
     server: Arc<Mutex<Server>>,
-    socket: &mut TcpStream,
+    socket: &mut S,
     socket_addr: SocketAddr,
-) {
+) -> bool {
     info!(
         "Received FragmentRequest for worker: {}",
         request.worker_name
@@ -200,33 +662,569 @@ async fn process_fragment_request(
             server.config.address.to_string(),
             server.config.port,
         );
-        server.register_worker(socket_addr, worker);
-        server.create_fragment_task()
+        let name = server.register_worker(socket_addr, worker);
+        let mut task = server.create_fragment_task_for(request.maximal_work_load);
+        if let Some(task) = &mut task {
+            if !request.supports_intensity_format(task.intensity_format) {
+                debug!(
+                    "{} does not send {} intensities, falling back to {}.",
+                    request.worker_name,
+                    task.intensity_format,
+                    IntensityFormat::default()
+                );
+                task.intensity_format = IntensityFormat::default();
+            }
+            server.mark_task_inflight(socket_addr, task.clone());
+        }
+        task.map(|task| FragmentTask {
+            assigned_name: Some(name),
+            ..task
+        })
     };
 
     match task {
         Some(task) => {
-            if let Err(e) = send_fragment_task(socket, &request.worker_name, &task).await {
+            let compression = request.task_compression();
+            if let Err(e) =
+                send_fragment_task(socket, &request.worker_name, &task, compression).await
+            {
                 error!("Failed to send fragment task: {}", e);
+                server.lock().unwrap().reclaim_task(&socket_addr);
+                return false;
             }
+            true
         }
         None => {
             info!("No more fragment tasks to send.");
+            false
         }
     }
 }
 
-async fn send_fragment_task(
-    socket: &mut TcpStream,
+async fn send_fragment_task<S: AsyncWrite + Unpin>(
+    socket: &mut S,
     worker_name: &str,
     task: &FragmentTask,
+    compression: Compression,
 ) -> NetworkingResult<()> {
     let serialized_task = task.to_json()?;
     let task_json = serde_json::to_string(&serialized_task)?;
-    let signature = [0u8; 16]; // Placeholder for actual signature logic
+    let signature = sign_payload(env::secret(), task_json.as_bytes(), &[]);
 
     info!("Sending fragment task to worker: {}", worker_name);
-    send_message(socket, task_json.as_bytes(), Some(&signature))
+    send_message(socket, task_json.as_bytes(), Some(&signature), compression).await
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::networking::{
+        read_message_raw, read_message_raw_with_limit, send_result, DEFAULT_MAX_MESSAGE_SIZE,
+    };
+    use shared::rendering::{headless::draw_fragment, on_graphics_thread};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    async fn start_server(max_workers: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut config = ServerConfig::new("127.0.0.1".to_string(), addr.port(), 300, 300, 3);
+        config.max_workers = max_workers;
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+
+        tokio::spawn(handle_connections(
+            Listener::Tcp(listener),
+            None,
+            server,
+            render_tx,
+            CancellationToken::new(),
+        ));
+        addr
+    }
+
+    #[tokio::test]
+    async fn connections_beyond_the_limit_are_rejected() {
+        let addr = start_server(2).await;
+
+        let mut accepted = Vec::new();
+        for _ in 0..2 {
+            accepted.push(TcpStream::connect(addr).await.unwrap());
+        }
+        let mut rejected = TcpStream::connect(addr).await.unwrap();
+
+        let message = read_message_raw(&mut rejected).await.unwrap();
+        let error = ErrorMessage::from_json(&message.json_message).unwrap();
+        assert!(error.message.contains("at most 2 workers"));
+
+        for socket in &mut accepted {
+            let pending = read_message_raw_with_limit(
+                socket,
+                DEFAULT_MAX_MESSAGE_SIZE,
+                Duration::from_millis(50),
+            )
+            .await;
+            assert!(matches!(pending, Err(NetworkingError::Timeout(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn a_stalled_tls_handshake_releases_its_slot() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let directory = std::env::temp_dir();
+        let cert_path = directory.join(format!("frakt-stalled-{}.crt", std::process::id()));
+        let key_path = directory.join(format!("frakt-stalled-{}.key", std::process::id()));
+        std::fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut config = ServerConfig::new("127.0.0.1".to_string(), addr.port(), 300, 300, 3);
+        config.max_workers = 1;
+        config.read_timeout = Duration::from_millis(100);
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        tokio::spawn(handle_connections(
+            Listener::Tcp(listener),
+            Some(tls::acceptor(&cert_path, &key_path).unwrap()),
+            server,
+            render_tx,
+            CancellationToken::new(),
+        ));
+
+        // never sends its ClientHello
+        let _stalled = TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let mut socket = tls::connector(None, true)
+            .unwrap()
+            .connect(tls::server_name("localhost").unwrap(), socket)
+            .await
+            .unwrap();
+        let request = FragmentRequest::new("worker".to_string(), 100)
+            .to_json()
+            .unwrap()
+            .to_string();
+        send_message(&mut socket, request.as_bytes(), None, Compression::None)
+            .await
+            .unwrap();
+
+        let message = read_message_raw(&mut socket).await.unwrap();
+        assert!(FragmentTask::from_json(&message.json_message).is_ok());
+    }
+
+    #[tokio::test]
+    async fn slots_are_released_when_a_worker_leaves() {
+        let addr = start_server(1).await;
+
+        let first = TcpStream::connect(addr).await.unwrap();
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let pending = read_message_raw_with_limit(
+            &mut second,
+            DEFAULT_MAX_MESSAGE_SIZE,
+            Duration::from_millis(50),
+        )
+        .await;
+        assert!(matches!(pending, Err(NetworkingError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn unknown_messages_are_answered_with_an_error() {
+        let addr = start_server(1).await;
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+
+        send_message(
+            &mut socket,
+            br#"{"Greeting": {"text": "hello"}}"#,
+            None,
+            Compression::None,
+        )
         .await
-        .map_err(Into::into)
+        .unwrap();
+
+        let message = read_message_raw(&mut socket).await.unwrap();
+        let error = ErrorMessage::from_json(&message.json_message).unwrap();
+        assert!(error.message.contains("unknown message type"));
+    }
+
+    #[tokio::test]
+    async fn tasks_are_compressed_for_the_workers_advertising_it() {
+        let addr = start_server(2).await;
+
+        for (capabilities, compression) in [
+            (vec!["zstd".to_string()], Compression::Zstd),
+            (Vec::new(), Compression::None),
+        ] {
+            let mut socket = TcpStream::connect(addr).await.unwrap();
+            let request =
+                FragmentRequest::with_capabilities("worker".to_string(), 100, capabilities)
+                    .to_json()
+                    .unwrap()
+                    .to_string();
+            send_message(&mut socket, request.as_bytes(), None, Compression::None)
+                .await
+                .unwrap();
+
+            let message = read_message_raw(&mut socket).await.unwrap();
+            assert_eq!(message.compression, compression);
+            assert!(verify_payload(
+                env::secret(),
+                message.json_message.as_bytes(),
+                &[],
+                &message.data
+            ));
+            assert!(FragmentTask::from_json(&message.json_message).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn connections_in_progress_are_drained_on_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = ServerConfig::new("127.0.0.1".to_string(), addr.port(), 300, 300, 3);
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        let shutdown = CancellationToken::new();
+        let handler = tokio::spawn(handle_connections(
+            Listener::Tcp(listener),
+            None,
+            server,
+            render_tx,
+            shutdown.clone(),
+        ));
+
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let request = FragmentRequest::new("worker".to_string(), 100)
+            .to_json()
+            .unwrap()
+            .to_string();
+        send_message(&mut socket, request.as_bytes(), None, Compression::None)
+            .await
+            .unwrap();
+        let message = read_message_raw(&mut socket).await.unwrap();
+        let task = FragmentTask::from_json(&message.json_message).unwrap();
+
+        shutdown.cancel();
+        let (result, data) = task.perform().unwrap();
+        let result = result.to_json().unwrap().to_string();
+        send_result(&mut socket, &result, &data, Compression::None)
+            .await
+            .unwrap();
+
+        let rendered = tokio::time::timeout(Duration::from_secs(1), render_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rendered.result.range, task.range);
+        tokio::time::timeout(Duration::from_secs(1), handler)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn fragments_beyond_the_render_channel_are_dropped_and_requeued() {
+        let config = ServerConfig::new("127.0.0.1".to_string(), 8787, 300, 300, 3);
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(1);
+        let server = create_server(&config, &render_tx);
+
+        let tasks: Vec<_> = (4000..4003)
+            .map(|port| {
+                let worker: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+                let mut server = server.lock().unwrap();
+                let task = server.next_task().unwrap();
+                server.mark_task_inflight(worker, task.clone());
+                (worker, task)
+            })
+            .collect();
+
+        for (worker, task) in tasks {
+            let (result, data) = task.perform().unwrap();
+            let json = result.to_json().unwrap().to_string();
+            let signature = sign_payload(env::secret(), json.as_bytes(), &data);
+            let payload = [signature.as_slice(), &data].concat();
+
+            // nothing reads the channel, a blocking send would never return
+            tokio::time::timeout(
+                Duration::from_secs(1),
+                process_fragment_result(
+                    result,
+                    &json,
+                    &payload,
+                    render_tx.clone(),
+                    worker,
+                    server.clone(),
+                ),
+            )
+            .await
+            .expect("the connection handler stalled on a full channel");
+        }
+
+        let server = server.lock().unwrap();
+        assert_eq!(server.dropped_fragments, 2);
+        assert_eq!(server.tasks.len(), 2);
+        assert_eq!(server.to_dto().dropped_fragments, 2);
+    }
+
+    #[tokio::test]
+    async fn workers_are_served_when_the_window_cannot_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = ServerConfig::new("127.0.0.1".to_string(), addr.port(), 300, 300, 3);
+        let (render_tx, render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        let connection_handler = tokio::spawn(handle_connections(
+            Listener::Tcp(listener),
+            None,
+            server,
+            render_tx,
+            CancellationToken::new(),
+        ));
+        // the graphics give up on the channel when they fail
+        drop(render_rx);
+        tokio::spawn(serve_with_graphics(
+            connection_handler,
+            async { Err(GraphicsError::EventLoop("no display".to_string())) },
+            CancellationToken::new(),
+        ));
+
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let request = FragmentRequest::new("worker".to_string(), 100)
+            .to_json()
+            .unwrap()
+            .to_string();
+        send_message(&mut socket, request.as_bytes(), None, Compression::None)
+            .await
+            .unwrap();
+        let message = read_message_raw(&mut socket).await.unwrap();
+
+        assert!(FragmentTask::from_json(&message.json_message).is_ok());
+    }
+
+    // on a single threaded runtime, a window holding up the runtime would starve the connections
+    #[tokio::test(flavor = "current_thread")]
+    async fn workers_are_served_while_the_window_is_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = ServerConfig::new("127.0.0.1".to_string(), addr.port(), 300, 300, 3);
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        let shutdown = CancellationToken::new();
+        let connection_handler = tokio::spawn(handle_connections(
+            Listener::Tcp(listener),
+            None,
+            server,
+            render_tx,
+            shutdown.clone(),
+        ));
+        // stands for the event loop, blocking its thread until the window is closed
+        let (close_window, window_closed) = std::sync::mpsc::channel::<()>();
+        let graphics = on_graphics_thread(move || {
+            let _ = window_closed.recv();
+            Ok(())
+        });
+        let serving = tokio::spawn(serve_with_graphics(connection_handler, graphics, shutdown));
+
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let request = FragmentRequest::new("worker".to_string(), 100)
+            .to_json()
+            .unwrap()
+            .to_string();
+        send_message(&mut socket, request.as_bytes(), None, Compression::None)
+            .await
+            .unwrap();
+        let message = read_message_raw(&mut socket).await.unwrap();
+        assert!(FragmentTask::from_json(&message.json_message).is_ok());
+        assert!(!serving.is_finished());
+
+        close_window.send(()).unwrap();
+        drop(socket);
+        tokio::time::timeout(Duration::from_secs(1), serving)
+            .await
+            .expect("closing the window did not shut the server down")
+            .unwrap();
+    }
+
+    // hands a task out to `worker` and computes it, the way the worker would send it back
+    fn perform_task(
+        server: &Arc<Mutex<Server>>,
+        worker: SocketAddr,
+    ) -> (FragmentTask, FragmentResult, String, Vec<u8>) {
+        let task = server.lock().unwrap().next_task().unwrap();
+        server
+            .lock()
+            .unwrap()
+            .mark_task_inflight(worker, task.clone());
+        let (result, data) = task.perform().unwrap();
+        let json = result.to_json().unwrap().to_string();
+        let signature = sign_payload(env::secret(), json.as_bytes(), &data);
+        let payload = [signature.as_slice(), &data].concat();
+        (task, result, json, payload)
+    }
+
+    fn is_queued(server: &Arc<Mutex<Server>>, task: &FragmentTask) -> bool {
+        let server = server.lock().unwrap();
+        server.inflight_tasks.is_empty()
+            && server
+                .tasks
+                .iter()
+                .any(|queued| queued.task_uuid == task.task_uuid)
+    }
+
+    #[tokio::test]
+    async fn a_result_with_a_bad_signature_leaves_its_task_queued() {
+        let config = ServerConfig::new("127.0.0.1".to_string(), 8787, 300, 300, 3);
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        let worker: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        let (task, result, json, mut payload) = perform_task(&server, worker);
+        payload[0] ^= 0x01;
+        process_fragment_result(
+            result,
+            &json,
+            &payload,
+            render_tx.clone(),
+            worker,
+            server.clone(),
+        )
+        .await;
+        assert!(is_queued(&server, &task));
+
+        let (task, result, json, _) = perform_task(&server, worker);
+        process_fragment_result(result, &json, &[0; 4], render_tx, worker, server.clone()).await;
+        assert!(is_queued(&server, &task));
+        assert!(render_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn rejected_results_are_not_recorded() {
+        let path = std::env::temp_dir().join(format!("frakt-rejected-{}.bin", std::process::id()));
+        let config = ServerConfig::new("127.0.0.1".to_string(), 8787, 300, 300, 3);
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        server.lock().unwrap().recorder = Some(Arc::new(SessionRecorder::create(&path).unwrap()));
+        let worker: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        let (_, result, json, mut payload) = perform_task(&server, worker);
+        payload[0] ^= 0x01;
+        process_fragment_result(
+            result,
+            &json,
+            &payload,
+            render_tx.clone(),
+            worker,
+            server.clone(),
+        )
+        .await;
+        let (task, result, json, payload) = perform_task(&server, worker);
+        process_fragment_result(result, &json, &payload, render_tx, worker, server.clone()).await;
+        server.lock().unwrap().recorder = None;
+
+        let session = read_session(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(session.len(), 1);
+        assert_eq!(session[0].task.task_uuid, task.task_uuid);
+    }
+
+    #[tokio::test]
+    async fn a_result_failing_its_checksum_leaves_its_task_queued() {
+        let config = ServerConfig::new("127.0.0.1".to_string(), 8787, 300, 300, 3);
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        let worker: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        // corrupted before it was signed, the signature holds but the checksum does not
+        let (task, result, json, payload) = perform_task(&server, worker);
+        let mut data = payload[SIGNATURE_SIZE..].to_vec();
+        data[0] ^= 0x01;
+        let signature = sign_payload(env::secret(), json.as_bytes(), &data);
+        let payload = [signature.as_slice(), &data].concat();
+        process_fragment_result(result, &json, &payload, render_tx, worker, server.clone()).await;
+
+        assert!(is_queued(&server, &task));
+        assert!(render_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn a_result_missing_pixels_leaves_its_task_queued() {
+        let config = ServerConfig::new("127.0.0.1".to_string(), 8787, 300, 300, 3);
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let server = create_server(&config, &render_tx);
+        let worker: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        let (task, mut result, _, payload) = perform_task(&server, worker);
+        let data = &payload[SIGNATURE_SIZE..payload.len() - task.intensity_format.pixel_size()];
+        result.checksum = None;
+        let json = result.to_json().unwrap().to_string();
+        let signature = sign_payload(env::secret(), json.as_bytes(), data);
+        let payload = [signature.as_slice(), data].concat();
+        process_fragment_result(result, &json, &payload, render_tx, worker, server.clone()).await;
+
+        assert!(is_queued(&server, &task));
+        assert!(render_rx.try_recv().is_err());
+    }
+
+    // draws every fragment that reaches the channel into a frame of the server's view
+    fn draw_received(
+        server: &Arc<Mutex<Server>>,
+        render_rx: &mut mpsc::Receiver<RenderingData>,
+    ) -> image::RgbImage {
+        let server = server.lock().unwrap();
+        let mut frame = image::RgbImage::new(server.config.width, server.config.height);
+        while let Ok(data) = render_rx.try_recv() {
+            draw_fragment(&mut frame, &data, &server.range);
+        }
+        frame
+    }
+
+    #[tokio::test]
+    async fn a_replayed_session_draws_the_recorded_frame() {
+        let path = std::env::temp_dir().join(format!("frakt-replay-{}.bin", std::process::id()));
+        let config = ServerConfig::new("127.0.0.1".to_string(), 8787, 90, 90, 3);
+        let worker: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let recording = create_server(&config, &render_tx);
+        recording.lock().unwrap().recorder =
+            Some(Arc::new(SessionRecorder::create(&path).unwrap()));
+        for _ in 0..4 {
+            let task = recording.lock().unwrap().next_task().unwrap();
+            recording
+                .lock()
+                .unwrap()
+                .mark_task_inflight(worker, task.clone());
+            let (result, data) = task.perform().unwrap();
+            let json = result.to_json().unwrap().to_string();
+            let signature = sign_payload(env::secret(), json.as_bytes(), &data);
+            let payload = [signature.as_slice(), &data].concat();
+
+            process_fragment_result(
+                result,
+                &json,
+                &payload,
+                render_tx.clone(),
+                worker,
+                recording.clone(),
+            )
+            .await;
+        }
+        recording.lock().unwrap().recorder = None;
+        let recorded = draw_received(&recording, &mut render_rx);
+
+        let session = read_session(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(session.len(), 4);
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let replaying = create_server(&config, &render_tx);
+        replay_session(session, replaying.clone(), render_tx).await;
+        let replayed = draw_received(&replaying, &mut render_rx);
+
+        assert!(recorded.pixels().any(|pixel| pixel.0 != [0, 0, 0]));
+        assert_eq!(replayed, recorded);
+    }
 }
@@ -0,0 +1,68 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use shared::{
+    models::pixel::pixel_intensity::IntensityFormat,
+    networking::{
+        compression::Compression, read_frame_with_limit, read_message_raw, send_result,
+        signature::SIGNATURE_SIZE, DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_READ_TIMEOUT,
+    },
+};
+use tokio::runtime::Runtime;
+
+// the frame of a fragment of `pixels` pixel intensities, as a worker sends it
+fn fragment_frame(runtime: &Runtime, pixels: usize) -> Vec<u8> {
+    let format = IntensityFormat::default();
+    let mut data = Vec::with_capacity(pixels * format.pixel_size());
+    for pixel in 0..pixels {
+        format.encode(0.0, pixel as f64 / pixels as f64, &mut data);
+    }
+
+    let mut frame = Vec::new();
+    runtime
+        .block_on(send_result(
+            &mut frame,
+            r#"{"FragmentResult":{}}"#,
+            &data,
+            Compression::None,
+        ))
+        .unwrap();
+    frame
+}
+
+fn read_fragment(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let format = IntensityFormat::default();
+    let mut group = c.benchmark_group("read_fragment");
+
+    for side in [64, 256, 1024] {
+        let frame = fragment_frame(&runtime, side * side);
+        group.throughput(Throughput::Bytes(frame.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("raw_message", side), &frame, |b, frame| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    let message = read_message_raw(&mut frame.as_slice()).await.unwrap();
+                    format.counts(&message.data[SIGNATURE_SIZE..])
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("frame", side), &frame, |b, frame| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    let frame = read_frame_with_limit(
+                        &mut frame.as_slice(),
+                        DEFAULT_MAX_MESSAGE_SIZE,
+                        DEFAULT_READ_TIMEOUT,
+                    )
+                    .await
+                    .unwrap();
+                    format.counts(&frame.data()[SIGNATURE_SIZE..])
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, read_fragment);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub runs: u32,
+    pub pixels_per_run: u64,
+    pub total_time: Duration,
+    pub mean_pixels_per_second: f64,
+    pub median_pixels_per_second: f64,
+}
+
+impl BenchmarkReport {
+    pub fn new(pixels_per_run: u64, run_times: &[Duration], total_time: Duration) -> Self {
+        let runs = run_times.len() as u32;
+        let total_pixels = pixels_per_run * runs as u64;
+        let mean_pixels_per_second = total_pixels as f64 / total_time.as_secs_f64();
+
+        let mut per_run: Vec<f64> = run_times
+            .iter()
+            .map(|time| pixels_per_run as f64 / time.as_secs_f64())
+            .collect();
+        per_run.sort_by(|a, b| a.total_cmp(b));
+        let median_pixels_per_second = match per_run.len() {
+            0 => 0.0,
+            len if len % 2 == 0 => (per_run[len / 2 - 1] + per_run[len / 2]) / 2.0,
+            len => per_run[len / 2],
+        };
+
+        Self {
+            runs,
+            pixels_per_run,
+            total_time,
+            mean_pixels_per_second,
+            median_pixels_per_second,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_mean_and_median_throughput() {
+        let run_times = [
+            Duration::from_millis(100),
+            Duration::from_millis(400),
+            Duration::from_millis(200),
+        ];
+
+        let report = BenchmarkReport::new(1000, &run_times, Duration::from_secs(1));
+
+        assert_eq!(report.runs, 3);
+        assert_eq!(report.mean_pixels_per_second, 3000.0);
+        assert_eq!(report.median_pixels_per_second, 5000.0);
+    }
+}
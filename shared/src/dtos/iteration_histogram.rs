@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+/// Buckets splitting the normalized iteration counts unless configured otherwise.
+pub const DEFAULT_HISTOGRAM_BUCKETS: usize = 32;
+
+/// Pixels received with a normalized iteration count in `[start, end)`, the last bucket
+/// includes 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub start: f64,
+    pub end: f64,
+    pub count: u64,
+}
+
+/// Distribution of the iteration counts of every fragment received since the server started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterationHistogram {
+    pub buckets: Vec<HistogramBucket>,
+}
+
+impl IterationHistogram {
+    pub fn new(bucket_count: usize) -> Self {
+        let bucket_count = bucket_count.max(1);
+        let width = 1.0 / bucket_count as f64;
+        let buckets = (0..bucket_count)
+            .map(|index| HistogramBucket {
+                start: index as f64 * width,
+                end: (index + 1) as f64 * width,
+                count: 0,
+            })
+            .collect();
+        Self { buckets }
+    }
+
+    /// Tallies normalized counts, out of range ones land in the first or last bucket and
+    /// non-finite ones are left out.
+    pub fn record(&mut self, iterations: &[f64]) {
+        let bucket_count = self.buckets.len();
+        for &t in iterations.iter().filter(|t| t.is_finite()) {
+            let index = (t.clamp(0.0, 1.0) * bucket_count as f64) as usize;
+            self.buckets[index.min(bucket_count - 1)].count += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.count).sum()
+    }
+}
+
+impl Default for IterationHistogram {
+    fn default() -> Self {
+        IterationHistogram::new(DEFAULT_HISTOGRAM_BUCKETS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(histogram: &IterationHistogram) -> Vec<u64> {
+        histogram
+            .buckets
+            .iter()
+            .map(|bucket| bucket.count)
+            .collect()
+    }
+
+    #[test]
+    fn known_counts_are_tallied_in_their_bucket() {
+        let mut histogram = IterationHistogram::new(4);
+
+        histogram.record(&[0.0, 0.1, 0.3, 0.5, 0.74]);
+        histogram.record(&[0.75, 0.99, 1.0]);
+
+        assert_eq!(counts(&histogram), vec![2, 1, 2, 3]);
+        assert_eq!(histogram.total(), 8);
+        assert_eq!(histogram.buckets[1].start, 0.25);
+        assert_eq!(histogram.buckets[1].end, 0.5);
+    }
+
+    #[test]
+    fn out_of_range_counts_are_clamped_and_non_finite_ones_skipped() {
+        let mut histogram = IterationHistogram::new(2);
+
+        histogram.record(&[-1.0, 2.0, f64::NAN, f64::INFINITY]);
+
+        assert_eq!(counts(&histogram), vec![1, 1]);
+    }
+
+    #[test]
+    fn buckets_serialize_with_their_bounds() {
+        let mut histogram = IterationHistogram::new(2);
+        histogram.record(&[0.25]);
+
+        let json = serde_json::to_string(&histogram).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"buckets":[{"start":0.0,"end":0.5,"count":1},{"start":0.5,"end":1.0,"count":0}]}"#
+        );
+    }
+}
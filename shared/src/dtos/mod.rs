@@ -1 +1,7 @@
+pub mod benchmark_report;
+pub mod iteration_histogram;
 pub mod rendering_data;
+pub mod server_dto;
+pub mod tile_timing;
+pub mod worker_metrics;
+pub mod worker_status;
@@ -1,8 +1,162 @@
-use crate::models::fragments::fragment_result::FragmentResult;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::{
+    models::{fragments::fragment_result::FragmentResult, resolution::Resolution},
+    networking::{error::NetworkingError, result::NetworkingResult},
+    rendering::color::{PaletteHandler, Rgb},
+};
+
+/// Bytes ahead of the pixels in [`RenderingData::to_binary`], the fragment's `nx` and `ny`.
+pub const BINARY_HEADER_SIZE: usize = 4;
+
+/// A fragment ready to be drawn. Its pixels serialize as `[r, g, b]` arrays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenderingData {
     pub result: FragmentResult,
     pub worker: String,
+    pub pixels: Vec<Rgb>,
     pub iterations: Vec<f64>,
 }
+
+impl RenderingData {
+    pub fn from_result(
+        result: FragmentResult,
+        worker: String,
+        iterations: Vec<f64>,
+        palette: &PaletteHandler,
+    ) -> Self {
+        let pixels = iterations
+            .iter()
+            .map(|&t| palette.calculate_color(t))
+            .collect();
+
+        Self {
+            result,
+            worker,
+            pixels,
+            iterations,
+        }
+    }
+
+    /// Packs the pixels for a binary websocket message, a fraction of the size of the JSON:
+    ///
+    /// | bytes  | content                                    |
+    /// |--------|--------------------------------------------|
+    /// | `0..2` | `nx`, big endian `u16`                     |
+    /// | `2..4` | `ny`, big endian `u16`                     |
+    /// | `4..`  | `nx * ny` pixels row by row, `r, g, b` bytes |
+    pub fn to_binary(&self) -> Vec<u8> {
+        let Resolution { nx, ny } = self.result.resolution;
+        let mut bytes = Vec::with_capacity(BINARY_HEADER_SIZE + self.pixels.len() * 3);
+        bytes.extend_from_slice(&nx.to_be_bytes());
+        bytes.extend_from_slice(&ny.to_be_bytes());
+        for &(r, g, b) in &self.pixels {
+            bytes.extend_from_slice(&[r, g, b]);
+        }
+        bytes
+    }
+
+    /// Reads back the resolution and pixels packed by [`RenderingData::to_binary`].
+    pub fn decode_binary(bytes: &[u8]) -> NetworkingResult<(Resolution, Vec<Rgb>)> {
+        if bytes.len() < BINARY_HEADER_SIZE {
+            return Err(NetworkingError::MalformedFrame(
+                "binary rendering data is too short to hold its resolution".to_string(),
+            ));
+        }
+        let (header, pixels) = bytes.split_at(BINARY_HEADER_SIZE);
+        let resolution = Resolution::new(
+            u16::from_be_bytes([header[0], header[1]]),
+            u16::from_be_bytes([header[2], header[3]]),
+        );
+        if pixels.len() != resolution.pixel_count() * 3 {
+            return Err(NetworkingError::MalformedFrame(format!(
+                "binary rendering data holds {} bytes of pixels, expected {}x{} pixels",
+                pixels.len(),
+                resolution.nx,
+                resolution.ny
+            )));
+        }
+
+        let pixels = pixels
+            .chunks_exact(3)
+            .map(|rgb| (rgb[0], rgb[1], rgb[2]))
+            .collect();
+        Ok((resolution, pixels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        pixel::pixel_data::PixelData, point::Point, range::Range, resolution::Resolution,
+        u8_data::U8Data,
+    };
+
+    use super::*;
+
+    fn rendering_data(resolution: Resolution) -> RenderingData {
+        let pixels = resolution.nx as u32 * resolution.ny as u32;
+        let result = FragmentResult::new(
+            U8Data::new(0, 16),
+            resolution,
+            Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)),
+            PixelData::new(16, pixels),
+        );
+        let iterations = (0..pixels).map(|i| i as f64 / pixels as f64).collect();
+        RenderingData::from_result(
+            result,
+            "worker-1".to_string(),
+            iterations,
+            &PaletteHandler::new(),
+        )
+    }
+
+    #[test]
+    fn every_iteration_is_colored_by_the_palette() {
+        let rendering_data = rendering_data(Resolution::new(8, 4));
+        let palette = PaletteHandler::new();
+
+        assert_eq!(rendering_data.pixels.len(), rendering_data.iterations.len());
+        for (pixel, &iterations) in rendering_data.pixels.iter().zip(&rendering_data.iterations) {
+            assert_eq!(*pixel, palette.calculate_color(iterations));
+        }
+    }
+
+    #[test]
+    fn rendering_data_round_trips_through_json() {
+        let rendering_data = rendering_data(Resolution::new(2, 1));
+
+        let json = serde_json::to_string(&rendering_data).unwrap();
+        let decoded: RenderingData = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.worker, rendering_data.worker);
+        assert_eq!(decoded.pixels, rendering_data.pixels);
+        assert_eq!(decoded.iterations, rendering_data.iterations);
+        assert_eq!(decoded.result.range, rendering_data.result.range);
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+        assert!(json.contains(&format!(
+            "[{},{},{}]",
+            rendering_data.pixels[0].0, rendering_data.pixels[0].1, rendering_data.pixels[0].2
+        )));
+    }
+
+    #[test]
+    fn binary_rendering_data_is_the_pixels_behind_a_header() {
+        let rendering_data = rendering_data(Resolution::new(16, 9));
+
+        let bytes = rendering_data.to_binary();
+        let (resolution, pixels) = RenderingData::decode_binary(&bytes).unwrap();
+
+        assert_eq!(bytes.len(), 16 * 9 * 3 + BINARY_HEADER_SIZE);
+        assert_eq!(resolution, Resolution::new(16, 9));
+        assert_eq!(pixels, rendering_data.pixels);
+    }
+
+    #[test]
+    fn truncated_binary_rendering_data_is_rejected() {
+        let bytes = rendering_data(Resolution::new(4, 4)).to_binary();
+
+        assert!(RenderingData::decode_binary(&bytes[..bytes.len() - 1]).is_err());
+        assert!(RenderingData::decode_binary(&bytes[..2]).is_err());
+    }
+}
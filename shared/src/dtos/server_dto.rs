@@ -0,0 +1,228 @@
+use std::{collections::HashMap, fmt::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dtos::{tile_timing::TileTiming, worker_metrics::WorkerMetrics, worker_status::WorkerStatus},
+    models::{fractal::fractal_descriptor::FractalDescriptor, range::Range},
+    networking::{server::ServerConfig, worker::Worker},
+};
+
+/// Snapshot of the live server state, keyed by the address of each connected worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerDto {
+    pub config: ServerConfig,
+    pub range: Range,
+    pub fractal: FractalDescriptor,
+    pub palette: String,
+    pub remaining_tiles: usize,
+    pub queued_tasks: usize,
+    pub inflight_tasks: usize,
+    pub workers: HashMap<String, Worker>,
+    pub worker_statuses: Vec<WorkerStatus>,
+    pub metrics: HashMap<String, WorkerMetrics>,
+    #[serde(default)]
+    pub dropped_fragments: u64,
+    /// Compute time of each fragment of the current view, a heatmap of its slow regions.
+    #[serde(default)]
+    pub tile_timings: Vec<TileTiming>,
+}
+
+impl ServerDto {
+    /// Renders the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut output = String::new();
+        let mut workers: Vec<_> = self.metrics.iter().collect();
+        workers.sort_by(|a, b| a.0.cmp(b.0));
+
+        write_metric_header(
+            &mut output,
+            "frakt_fragments_completed_total",
+            "counter",
+            "Fragments computed by each worker.",
+        );
+        for (worker, metrics) in &workers {
+            let _ = writeln!(
+                output,
+                "frakt_fragments_completed_total{{worker=\"{}\"}} {}",
+                escape_label_value(worker),
+                metrics.completed_fragments
+            );
+        }
+
+        write_metric_header(
+            &mut output,
+            "frakt_pixels_computed_total",
+            "counter",
+            "Pixels computed by each worker.",
+        );
+        for (worker, metrics) in &workers {
+            let _ = writeln!(
+                output,
+                "frakt_pixels_computed_total{{worker=\"{}\"}} {}",
+                escape_label_value(worker),
+                metrics.total_pixels
+            );
+        }
+
+        write_metric_header(
+            &mut output,
+            "frakt_fragments_dropped_total",
+            "counter",
+            "Fragments dropped because rendering fell behind.",
+        );
+        let _ = writeln!(
+            output,
+            "frakt_fragments_dropped_total {}",
+            self.dropped_fragments
+        );
+
+        let connected = self
+            .worker_statuses
+            .iter()
+            .filter(|status| !status.stale)
+            .count();
+        for (name, help, value) in [
+            (
+                "frakt_tasks_queued",
+                "Tasks waiting for a worker.",
+                self.queued_tasks,
+            ),
+            (
+                "frakt_tasks_inflight",
+                "Tasks being computed.",
+                self.inflight_tasks,
+            ),
+            (
+                "frakt_tiles_remaining",
+                "Tiles not turned into tasks yet.",
+                self.remaining_tiles,
+            ),
+            (
+                "frakt_workers_connected",
+                "Workers heard from recently.",
+                connected,
+            ),
+        ] {
+            write_metric_header(&mut output, name, "gauge", help);
+            let _ = writeln!(output, "{} {}", name, value);
+        }
+
+        output
+    }
+}
+
+fn write_metric_header(output: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, kind);
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        dtos::worker_metrics::WorkerMetrics, models::fractal::mandelbrot::Mandelbrot,
+        models::point::Point,
+    };
+
+    use super::*;
+
+    fn dto() -> ServerDto {
+        let metrics = HashMap::from([
+            (
+                "worker-1".to_string(),
+                WorkerMetrics {
+                    completed_fragments: 3,
+                    total_pixels: 300,
+                },
+            ),
+            (
+                "quoted \"worker\"".to_string(),
+                WorkerMetrics {
+                    completed_fragments: 1,
+                    total_pixels: 50,
+                },
+            ),
+        ]);
+        let status = |name: &str, stale| WorkerStatus {
+            address: "127.0.0.1:4242".to_string(),
+            name: name.to_string(),
+            maximal_work_load: 500,
+            seconds_since_last_seen: 1.0,
+            stale,
+        };
+
+        ServerDto {
+            config: ServerConfig::new("localhost".to_string(), 8787, 300, 300, 3),
+            range: Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)),
+            fractal: FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            palette: "classic".to_string(),
+            remaining_tiles: 4,
+            queued_tasks: 2,
+            inflight_tasks: 1,
+            workers: HashMap::new(),
+            worker_statuses: vec![status("worker-1", false), status("gone", true)],
+            metrics,
+            dropped_fragments: 5,
+            tile_timings: Vec::new(),
+        }
+    }
+
+    // a sample is `name{label="value",...} number`, comments start with `#`
+    fn parse_sample(line: &str) -> (String, f64) {
+        let (series, value) = line.rsplit_once(' ').expect("sample without a value");
+        let name = match series.split_once('{') {
+            Some((name, labels)) => {
+                assert!(labels.ends_with('}'), "unterminated labels: {}", line);
+                assert!(labels.contains("=\""), "malformed labels: {}", line);
+                name
+            }
+            None => series,
+        };
+        assert!(
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "invalid metric name: {}",
+            name
+        );
+        (name.to_string(), value.parse().expect("non numeric value"))
+    }
+
+    #[test]
+    fn prometheus_output_is_valid_exposition_text() {
+        let output = dto().to_prometheus();
+
+        let mut names = HashSet::new();
+        for line in output.lines() {
+            if let Some(comment) = line.strip_prefix('#') {
+                assert!(comment.starts_with(" HELP ") || comment.starts_with(" TYPE "));
+                continue;
+            }
+            names.insert(parse_sample(line).0);
+        }
+
+        for name in [
+            "frakt_fragments_completed_total",
+            "frakt_pixels_computed_total",
+            "frakt_fragments_dropped_total",
+            "frakt_tasks_queued",
+            "frakt_tasks_inflight",
+            "frakt_tiles_remaining",
+            "frakt_workers_connected",
+        ] {
+            assert!(names.contains(name), "missing {}", name);
+        }
+        assert!(output.contains("frakt_fragments_completed_total{worker=\"worker-1\"} 3\n"));
+        assert!(output.contains("{worker=\"quoted \\\"worker\\\"\"} 1\n"));
+        assert!(output.contains("frakt_tasks_queued 2\n"));
+        assert!(output.contains("frakt_fragments_dropped_total 5\n"));
+        assert!(output.contains("frakt_workers_connected 1\n"));
+    }
+}
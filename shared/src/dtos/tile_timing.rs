@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::range::Range;
+
+/// Time a worker spent computing the fragment covering `range`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TileTiming {
+    pub range: Range,
+    pub compute_ms: u32,
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Work a worker has delivered since the server started.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerMetrics {
+    pub completed_fragments: u64,
+    pub total_pixels: u64,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Health of a worker, as last heard from by the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerStatus {
+    pub address: String,
+    pub name: String,
+    pub maximal_work_load: u32,
+    pub seconds_since_last_seen: f64,
+    pub stale: bool,
+}
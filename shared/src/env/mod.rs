@@ -1,3 +1,18 @@
+use std::sync::OnceLock;
+
+pub const SECRET_ENV_VAR: &str = "FRAKT_SECRET";
+
 pub fn init() {
     dotenv::dotenv().ok();
 }
+
+/// Key shared by the server and its workers to sign messages, read from the environment the
+/// first time it is needed. Empty when unset, which anyone can sign with.
+pub fn secret() -> &'static [u8] {
+    static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+    SECRET.get_or_init(|| {
+        std::env::var(SECRET_ENV_VAR)
+            .map(String::into_bytes)
+            .unwrap_or_default()
+    })
+}
@@ -1,7 +1,7 @@
-use chrono::Local;
+use chrono::{DateTime, Local};
 use colored::Colorize;
 use env_logger::{Builder, Env};
-use log::Level;
+use log::{Level, Record};
 use std::io::Write;
 
 pub fn init() {
@@ -29,3 +29,45 @@ pub fn init_with_level(level_filter: &str) {
         .parse_env(Env::default().default_filter_or(level_filter))
         .init();
 }
+
+/// Logs one JSON object per line, for log aggregation systems.
+pub fn init_json(level_filter: &str) {
+    Builder::new()
+        .format(|buf, record| writeln!(buf, "{}", json_record(record, Local::now())))
+        .parse_env(Env::default().default_filter_or(level_filter))
+        .init();
+}
+
+fn json_record(record: &Record, timestamp: DateTime<Local>) -> serde_json::Value {
+    serde_json::json!({
+        "timestamp": timestamp.to_rfc3339(),
+        "level": record.level().to_string(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_records_hold_the_expected_fields() {
+        let timestamp = Local::now();
+        let line = json_record(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("frakt::server")
+                .args(format_args!("worker {} is \"stale\"", 42))
+                .build(),
+            timestamp,
+        )
+        .to_string();
+
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["level"], "WARN");
+        assert_eq!(parsed["target"], "frakt::server");
+        assert_eq!(parsed["message"], "worker 42 is \"stale\"");
+        assert_eq!(parsed["timestamp"], timestamp.to_rfc3339());
+    }
+}
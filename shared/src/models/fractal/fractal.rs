@@ -1,3 +1,11 @@
+use complex_rs::complex::Complex;
+
 pub trait Fractal {
     fn generate(&self, max_iterations: u32, x: f64, y: f64) -> (f64, f64);
+
+    /// Every `z` the iteration of the point goes through, one per iteration `generate` counts.
+    /// Meant for debugging a single pixel, fractals without a trace return none.
+    fn generate_trace(&self, _max_iterations: u32, _x: f64, _y: f64) -> Vec<Complex> {
+        Vec::new()
+    }
 }
@@ -1,17 +1,116 @@
+use std::fmt;
+
+use complex_rs::complex::Complex;
 use serde::{Deserialize, Serialize};
 
+use crate::models::{point::Point, range::Range};
+
 use super::{
-    iterated_sin_z::IteratedSinZ, julia::Julia, mandelbrot::Mandelbrot,
-    newton_raphson_3::NewtonRaphsonZ3, newton_raphson_4::NewtonRaphsonZ4,
-    nova_newton_raphson_z3::NovaNewtonRaphsonZ3, nova_newton_raphson_z4::NovaNewtonRaphsonZ4,
+    fractal::Fractal, iterated_sin_z::IteratedSinZ, julia::Julia, mandelbrot::Mandelbrot,
+    newton_raphson::NewtonRaphson, nova_newton_raphson_z3::NovaNewtonRaphsonZ3,
+    nova_newton_raphson_z4::NovaNewtonRaphsonZ4,
 };
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFractalError(pub String);
+
+impl fmt::Display for UnknownFractalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown fractal: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownFractalError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FractalDescriptor {
     Julia(Julia),
     Mandelbrot(Mandelbrot),
     IteratedSinZ(IteratedSinZ),
-    NewtonRaphsonZ3(NewtonRaphsonZ3),
-    NewtonRaphsonZ4(NewtonRaphsonZ4),
+    NewtonRaphson(NewtonRaphson),
     NovaNewtonRapshonZ3(NovaNewtonRaphsonZ3),
     NovaNewtonRapshonZ4(NovaNewtonRaphsonZ4),
 }
+
+impl FractalDescriptor {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FractalDescriptor::Julia(_) => "julia",
+            FractalDescriptor::Mandelbrot(_) => "mandelbrot",
+            FractalDescriptor::IteratedSinZ(_) => "iterated_sin_z",
+            FractalDescriptor::NewtonRaphson(newton_raphson) => match newton_raphson.degree {
+                3 => "newton_raphson_z3",
+                4 => "newton_raphson_z4",
+                _ => "newton_raphson",
+            },
+            FractalDescriptor::NovaNewtonRapshonZ3(_) => "nova_newton_raphson_z3",
+            FractalDescriptor::NovaNewtonRapshonZ4(_) => "nova_newton_raphson_z4",
+        }
+    }
+
+    /// View framing the interesting part of the fractal, used when switching to it.
+    pub fn default_range(&self) -> Range {
+        let (min, max) = match self {
+            FractalDescriptor::Mandelbrot(_) => (Point::new(-2.0, -1.5), Point::new(1.0, 1.5)),
+            FractalDescriptor::Julia(_) => (Point::new(-1.5, -1.5), Point::new(1.5, 1.5)),
+            // the view these fractals have always been rendered with
+            FractalDescriptor::IteratedSinZ(_)
+            | FractalDescriptor::NewtonRaphson(_)
+            | FractalDescriptor::NovaNewtonRapshonZ3(_)
+            | FractalDescriptor::NovaNewtonRapshonZ4(_) => {
+                (Point::new(-1.2, -1.2), Point::new(1.2, 1.2))
+            }
+        };
+        Range::new(min, max)
+    }
+
+    pub fn as_fractal(&self) -> &dyn Fractal {
+        match self {
+            FractalDescriptor::Julia(julia) => julia,
+            FractalDescriptor::Mandelbrot(mandelbrot) => mandelbrot,
+            FractalDescriptor::IteratedSinZ(iterated_sin_z) => iterated_sin_z,
+            FractalDescriptor::NewtonRaphson(newton_raphson) => newton_raphson,
+            FractalDescriptor::NovaNewtonRapshonZ3(nova_newton_raphson) => nova_newton_raphson,
+            FractalDescriptor::NovaNewtonRapshonZ4(nova_newton_raphson) => nova_newton_raphson,
+        }
+    }
+
+    pub fn generate(&self, max_iterations: u32, x: f64, y: f64) -> (f64, f64) {
+        self.as_fractal().generate(max_iterations, x, y)
+    }
+
+    pub fn generate_trace(&self, max_iterations: u32, x: f64, y: f64) -> Vec<Complex> {
+        self.as_fractal().generate_trace(max_iterations, x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_fractal() -> Vec<FractalDescriptor> {
+        vec![
+            FractalDescriptor::Julia(Julia::new(Complex::new(0.285, 0.013), 2.0)),
+            FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            FractalDescriptor::IteratedSinZ(IteratedSinZ::new(Complex::new(1.0, 0.3))),
+            FractalDescriptor::NewtonRaphson(NewtonRaphson::new(3)),
+            FractalDescriptor::NewtonRaphson(NewtonRaphson::new(4)),
+            FractalDescriptor::NovaNewtonRapshonZ3(NovaNewtonRaphsonZ3::new()),
+            FractalDescriptor::NovaNewtonRapshonZ4(NovaNewtonRaphsonZ4::new()),
+        ]
+    }
+
+    #[test]
+    fn every_fractal_generates_finite_values() {
+        for descriptor in every_fractal() {
+            let (zn, count) = descriptor.as_fractal().generate(64, 0.3, -0.4);
+
+            assert!(
+                zn.is_finite() && count.is_finite(),
+                "{} gave ({}, {})",
+                descriptor.name(),
+                zn,
+                count
+            );
+        }
+    }
+}
@@ -3,27 +3,90 @@ use serde::{Deserialize, Serialize};
 
 use super::fractal::Fractal;
 
+pub const DEFAULT_ESCAPE_RADIUS_SQ: f64 = 50.0;
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct IteratedSinZ {
     pub c: Complex,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escape_radius_sq: Option<f64>,
 }
 
 impl IteratedSinZ {
     pub fn new(c: Complex) -> Self {
-        Self { c }
+        Self {
+            c,
+            escape_radius_sq: None,
+        }
+    }
+
+    pub fn with_escape_radius_sq(c: Complex, escape_radius_sq: f64) -> Self {
+        Self {
+            c,
+            escape_radius_sq: Some(escape_radius_sq),
+        }
+    }
+
+    pub fn escape_radius_sq(&self) -> f64 {
+        self.escape_radius_sq.unwrap_or(DEFAULT_ESCAPE_RADIUS_SQ)
     }
 }
 
 impl Fractal for IteratedSinZ {
     fn generate(&self, max_iterations: u32, x: f64, y: f64) -> (f64, f64) {
         let mut z = Complex::new(x, y);
+        let escape_radius_sq = self.escape_radius_sq();
 
         let mut i = 0;
-        while i < max_iterations && z.arg_sq() < 50.0 {
+        while i < max_iterations && z.arg_sq() < escape_radius_sq {
             z = z.sin() * self.c;
             i += 1;
         }
 
         return (z.arg_sq(), i as f64);
     }
+
+    fn generate_trace(&self, max_iterations: u32, x: f64, y: f64) -> Vec<Complex> {
+        let mut z = Complex::new(x, y);
+        let escape_radius_sq = self.escape_radius_sq();
+
+        let mut trace = Vec::new();
+        while (trace.len() as u32) < max_iterations && z.arg_sq() < escape_radius_sq {
+            z = z.sin() * self.c;
+            trace.push(z);
+        }
+
+        trace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_missing_escape_radius_falls_back_to_the_default() {
+        let fractal: IteratedSinZ = serde_json::from_str(r#"{"c":{"re":1.0,"im":0.3}}"#).unwrap();
+
+        assert_eq!(fractal.escape_radius_sq, None);
+        assert_eq!(fractal.escape_radius_sq(), DEFAULT_ESCAPE_RADIUS_SQ);
+        assert_eq!(DEFAULT_ESCAPE_RADIUS_SQ, 50.0);
+    }
+
+    #[test]
+    fn the_escape_radius_changes_the_iteration_count() {
+        let c = Complex::new(1.0, 0.3);
+        let small = IteratedSinZ::with_escape_radius_sq(c, 4.0);
+        let large = IteratedSinZ::with_escape_radius_sq(c, 1e6);
+
+        let (_, small_count) = small.generate(64, 1.5, 1.5);
+        let (_, large_count) = large.generate(64, 1.5, 1.5);
+
+        assert!(
+            small_count < large_count,
+            "{} >= {}",
+            small_count,
+            large_count
+        );
+    }
 }
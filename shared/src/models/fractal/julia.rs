@@ -30,4 +30,17 @@ impl Fractal for Julia {
 
         return (z.arg_sq(), i as f64);
     }
+
+    fn generate_trace(&self, max_iterations: u32, x: f64, y: f64) -> Vec<Complex> {
+        let mut z = Complex::new(x, y);
+
+        let mut trace = Vec::new();
+        while (trace.len() as u32) < max_iterations && z.arg_sq() < self.divergence_threshold_square
+        {
+            z = z * z + self.c;
+            trace.push(z);
+        }
+
+        trace
+    }
 }
@@ -25,4 +25,39 @@ impl Fractal for Mandelbrot {
 
         return (z.arg_sq(), i as f64);
     }
+
+    fn generate_trace(&self, max_iterations: u32, x: f64, y: f64) -> Vec<Complex> {
+        let mut z = Complex::new(0.0, 0.0);
+        let c = Complex::new(x, y);
+
+        let mut trace = Vec::new();
+        while (trace.len() as u32) < max_iterations && z.arg_sq() < 4.0 {
+            z = z * z + c;
+            trace.push(z);
+        }
+
+        trace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_trace_of_an_escaping_point_has_one_z_per_iteration() {
+        let fractal = Mandelbrot::new();
+
+        let (zn, count) = fractal.generate(256, 0.3, 0.6);
+        let trace = fractal.generate_trace(256, 0.3, 0.6);
+
+        assert!(count < 256.0);
+        assert_eq!(trace.len(), count as usize);
+        assert_eq!(trace.last().unwrap().arg_sq(), zn);
+    }
+
+    #[test]
+    fn the_trace_of_a_bounded_point_stops_at_the_iteration_limit() {
+        assert_eq!(Mandelbrot::new().generate_trace(64, -0.1, 0.1).len(), 64);
+    }
 }
@@ -3,8 +3,7 @@ pub mod fractal_descriptor;
 pub mod iterated_sin_z;
 pub mod julia;
 pub mod mandelbrot;
-pub mod newton_raphson_3;
-pub mod newton_raphson_4;
+pub mod newton_raphson;
 pub mod nova_newton_raphson_z3;
 pub mod nova_newton_raphson_z4;
 pub mod utils;
@@ -0,0 +1,121 @@
+use complex_rs::complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use super::{fractal::Fractal, utils};
+
+/// Newton's method on `z^degree - 1`, colored by the root each point converges to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct NewtonRaphson {
+    pub degree: u32,
+}
+
+impl NewtonRaphson {
+    pub fn new(degree: u32) -> Self {
+        Self { degree }
+    }
+
+    // a null degree has no root to converge to, it is taken as 1
+    fn degree(&self) -> i32 {
+        self.degree.clamp(1, i32::MAX as u32) as i32
+    }
+
+    fn fz(&self, z: Complex) -> Complex {
+        z.powi(self.degree()) - Complex::new(1.0, 0.0)
+    }
+
+    fn dfz(&self, z: Complex) -> Complex {
+        Complex::new(self.degree() as f64, 0.0) * z.powi(self.degree() - 1)
+    }
+}
+
+impl Fractal for NewtonRaphson {
+    fn generate(&self, max_iterations: u32, x: f64, y: f64) -> (f64, f64) {
+        let mut z = Complex::new(x, y);
+        let mut zn_next;
+        let epsilon = 1e-6;
+        let mut i = 0;
+
+        loop {
+            zn_next = z - (self.fz(z) / self.dfz(z));
+            if (zn_next - z).arg_sq() < epsilon || i >= max_iterations {
+                break;
+            }
+            z = zn_next;
+            i += 1;
+        }
+
+        let zn = z.arg();
+        let count = if i < max_iterations {
+            utils::convergence_value(z.arg_sq(), epsilon, i, max_iterations)
+        } else {
+            1.0
+        };
+
+        (zn, i as f64 * count)
+    }
+
+    fn generate_trace(&self, max_iterations: u32, x: f64, y: f64) -> Vec<Complex> {
+        let mut z = Complex::new(x, y);
+        let epsilon = 1e-6;
+
+        let mut trace = Vec::new();
+        while (trace.len() as u32) < max_iterations {
+            let zn_next = z - (self.fz(z) / self.dfz(z));
+            if (zn_next - z).arg_sq() < epsilon {
+                break;
+            }
+            z = zn_next;
+            trace.push(z);
+        }
+
+        trace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::models::fractal::fractal_descriptor::FractalDescriptor;
+
+    use super::*;
+
+    #[test]
+    fn the_degree_survives_serialization() {
+        let descriptor = FractalDescriptor::NewtonRaphson(NewtonRaphson::new(5));
+
+        let json = serde_json::to_string(&descriptor).unwrap();
+        assert_eq!(json, r#"{"NewtonRaphson":{"degree":5}}"#);
+        match serde_json::from_str(&json).unwrap() {
+            FractalDescriptor::NewtonRaphson(fractal) => assert_eq!(fractal.degree, 5),
+            other => panic!("deserialized as {:?}", other),
+        }
+    }
+
+    #[test]
+    fn points_near_a_root_converge_to_it() {
+        for degree in [3, 5, 6] {
+            let fractal = NewtonRaphson::new(degree);
+            for root in 0..degree {
+                // the roots of z^n - 1 sit at angles k/n of a full turn
+                let angle = root as f64 / degree as f64;
+                let (x, y) = (
+                    1.05 * (2.0 * PI * angle).cos(),
+                    1.05 * (2.0 * PI * angle).sin(),
+                );
+
+                let (zn, count) = fractal.generate(64, x, y);
+
+                let turn = (zn - angle).abs();
+                assert!(
+                    turn.min(1.0 - turn) < 1e-3,
+                    "degree {}: {} converged to {}",
+                    degree,
+                    angle,
+                    zn
+                );
+                assert!(count < 64.0, "degree {} did not converge", degree);
+            }
+        }
+    }
+}
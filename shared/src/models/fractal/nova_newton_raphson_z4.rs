@@ -39,4 +39,22 @@ impl Fractal for NovaNewtonRaphsonZ4 {
 
         return (0.0, i as f64);
     }
+
+    fn generate_trace(&self, max_iterations: u32, x: f64, y: f64) -> Vec<Complex> {
+        let c = Complex::new(x, y);
+        let mut z = Complex::new(1.0, 0.0);
+        let epsilon = 1e-6;
+
+        let mut trace = Vec::new();
+        while (trace.len() as u32) < max_iterations {
+            let zn_next = z - (self.fz(z) / self.dfz(z)) + c;
+            if (zn_next - z).arg_sq() < epsilon {
+                break;
+            }
+            z = zn_next;
+            trace.push(z);
+        }
+
+        trace
+    }
 }
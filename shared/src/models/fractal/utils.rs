@@ -6,3 +6,18 @@ pub fn convergence_value(pzn: f64, threshold: f64, count: u32, nmax: u32) -> f64
         1.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convergence_value_is_pinned() {
+        assert_eq!(convergence_value(1e-8, 1e-6, 5, 64), 0.03323622572225521);
+    }
+
+    #[test]
+    fn points_out_of_iterations_saturate() {
+        assert_eq!(convergence_value(1e-8, 1e-6, 64, 64), 1.0);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use super::fragment::Fragment;
+
+/// Tells the peer why its request was turned down before the connection is closed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorMessage {
+    pub message: String,
+}
+
+impl ErrorMessage {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Fragment for ErrorMessage {
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        let wrapped = serde_json::json!({ "Error": self });
+        serde_json::to_value(&wrapped)
+    }
+
+    fn from_json(fragment: &str) -> Result<Self, serde_json::Error> {
+        let v: serde_json::Value = serde_json::from_str(fragment)?;
+        serde_json::from_value(v["Error"].clone())
+    }
+}
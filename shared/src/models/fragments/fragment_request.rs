@@ -1,10 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    models::pixel::pixel_intensity::IntensityFormat, networking::compression::Compression,
+};
+
 use super::fragment::Fragment;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FragmentRequest {
     pub worker_name: String,
     pub maximal_work_load: u32,
+    /// What the worker understands beyond the original protocol, e.g. `zstd` or `f64`. Workers
+    /// predating it send none and get uncompressed tasks with the default intensity format.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
 }
 
 impl FragmentRequest {
@@ -12,8 +20,41 @@ impl FragmentRequest {
         Self {
             worker_name,
             maximal_work_load: maximum_work_load,
+            capabilities: Vec::new(),
         }
     }
+
+    pub fn with_capabilities(
+        worker_name: String,
+        maximum_work_load: u32,
+        capabilities: Vec<String>,
+    ) -> Self {
+        Self {
+            worker_name,
+            maximal_work_load: maximum_work_load,
+            capabilities,
+        }
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities
+            .iter()
+            .any(|supported| supported.eq_ignore_ascii_case(capability))
+    }
+
+    /// The best codec the worker decodes, tasks go uncompressed to those naming none.
+    pub fn task_compression(&self) -> Compression {
+        [Compression::Zstd, Compression::Gzip]
+            .into_iter()
+            .find(|compression| self.supports(&compression.to_string()))
+            .unwrap_or(Compression::None)
+    }
+
+    /// Whether the worker can send its pixel intensities in `format`, every worker knows the
+    /// default one.
+    pub fn supports_intensity_format(&self, format: IntensityFormat) -> bool {
+        format == IntensityFormat::default() || self.supports(&format.to_string())
+    }
 }
 
 impl Fragment for FragmentRequest {
@@ -27,3 +68,44 @@ impl Fragment for FragmentRequest {
         serde_json::from_value(v["FragmentRequest"].clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_requests_have_no_capabilities() {
+        let request = FragmentRequest::from_json(
+            r#"{"FragmentRequest":{"worker_name":"w","maximal_work_load":10}}"#,
+        )
+        .unwrap();
+
+        assert!(request.capabilities.is_empty());
+        assert_eq!(request.task_compression(), Compression::None);
+        assert!(request.supports_intensity_format(IntensityFormat::default()));
+        assert!(!request.supports_intensity_format(IntensityFormat::F64));
+        assert_eq!(
+            FragmentRequest::new("w".to_string(), 10).to_json().unwrap(),
+            serde_json::json!({"FragmentRequest": {"worker_name": "w", "maximal_work_load": 10}})
+        );
+    }
+
+    #[test]
+    fn the_best_codec_advertised_is_picked() {
+        let request = |capabilities: &[&str]| {
+            FragmentRequest::with_capabilities(
+                "w".to_string(),
+                10,
+                capabilities.iter().map(|c| c.to_string()).collect(),
+            )
+        };
+
+        assert_eq!(request(&["gzip"]).task_compression(), Compression::Gzip);
+        assert_eq!(
+            request(&["gzip", "ZSTD"]).task_compression(),
+            Compression::Zstd
+        );
+        assert_eq!(request(&["brotli"]).task_compression(), Compression::None);
+        assert!(request(&["f64"]).supports_intensity_format(IntensityFormat::F64));
+    }
+}
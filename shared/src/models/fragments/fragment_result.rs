@@ -1,16 +1,29 @@
-use crate::models::{
-    pixel::pixel_data::PixelData, range::Range, resolution::Resolution, u8_data::U8Data,
+use crate::{
+    models::{pixel::pixel_data::PixelData, range::Range, resolution::Resolution, u8_data::U8Data},
+    rendering::color::PaletteHandler,
 };
 
+use image::{Rgb, RgbImage};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
 
-use super::fragment::Fragment;
+use super::{fragment::Fragment, fragment_task::FragmentTask};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FragmentResult {
     pub id: U8Data,
     pub resolution: Resolution,
     pub range: Range,
     pub pixels: PixelData,
+    /// CRC32 of the pixel intensities, absent when the worker did not compute one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<u32>,
+    /// `task_uuid` of the task computed, absent from workers that predate it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_uuid: Option<Uuid>,
+    /// Milliseconds the worker spent computing the fragment, absent when it did not say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_ms: Option<u32>,
 }
 
 impl FragmentResult {
@@ -20,8 +33,57 @@ impl FragmentResult {
             resolution,
             range,
             pixels,
+            checksum: None,
+            task_uuid: None,
+            compute_ms: None,
         }
     }
+
+    /// Marks the result as the answer to the task with `task_uuid`.
+    pub fn for_task(mut self, task_uuid: Uuid) -> Self {
+        self.task_uuid = Some(task_uuid);
+        self
+    }
+
+    pub fn with_compute_time(mut self, elapsed: Duration) -> Self {
+        self.compute_ms = Some(u32::try_from(elapsed.as_millis()).unwrap_or(u32::MAX));
+        self
+    }
+
+    /// Stamps the result with the CRC32 of `data`, the pixel intensities sent along with it.
+    pub fn with_checksum(mut self, data: &[u8]) -> Self {
+        self.checksum = Some(crc32fast::hash(data));
+        self
+    }
+
+    /// Whether `data` arrived intact, results without a checksum are taken as they are.
+    pub fn verify_checksum(&self, data: &[u8]) -> bool {
+        self.checksum
+            .is_none_or(|checksum| checksum == crc32fast::hash(data))
+    }
+
+    /// Whether this result answers `task`: same resolution and range, with one pixel
+    /// intensity per pixel laid out after the task's id.
+    pub fn matches_task(&self, task: &FragmentTask) -> bool {
+        self.resolution == task.resolution
+            && self.range == task.range
+            && self.pixels.offset == task.id.count
+            && self.pixels.count as usize == task.resolution.pixel_count()
+    }
+
+    /// Paints the fragment on its own, `counts` holding the normalized iteration count of
+    /// each pixel row by row. Pixels without a count are left black.
+    pub fn to_image(&self, counts: &[f64], palette: &PaletteHandler) -> RgbImage {
+        let Resolution { nx, ny } = self.resolution;
+        let mut image = RgbImage::new(nx as u32, ny as u32);
+
+        for (pixel, &t) in image.pixels_mut().zip(counts) {
+            let (r, g, b) = palette.calculate_color(t);
+            *pixel = Rgb([r, g, b]);
+        }
+
+        image
+    }
 }
 
 impl Fragment for FragmentResult {
@@ -35,3 +97,88 @@ impl Fragment for FragmentResult {
         serde_json::from_value(v["FragmentResult"].clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        fractal::{fractal_descriptor::FractalDescriptor, mandelbrot::Mandelbrot},
+        point::Point,
+    };
+
+    use super::*;
+
+    fn task() -> FragmentTask {
+        FragmentTask::new(
+            U8Data::new(0, 16),
+            FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            64,
+            Resolution::new(8, 4),
+            Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)),
+        )
+    }
+
+    fn result_for(task: &FragmentTask) -> FragmentResult {
+        FragmentResult::new(
+            task.id.clone(),
+            task.resolution,
+            task.range,
+            PixelData::new(task.id.count, 8 * 4),
+        )
+    }
+
+    #[test]
+    fn result_computed_for_a_task_matches_it() {
+        let task = task();
+
+        assert!(result_for(&task).matches_task(&task));
+    }
+
+    #[test]
+    fn mismatched_results_are_detected() {
+        let task = task();
+
+        let mut resolution = result_for(&task);
+        resolution.resolution = Resolution::new(4, 8);
+        let mut range = result_for(&task);
+        range.range = Range::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        let mut count = result_for(&task);
+        count.pixels.count -= 1;
+
+        assert!(!resolution.matches_task(&task));
+        assert!(!range.matches_task(&task));
+        assert!(!count.matches_task(&task));
+    }
+
+    #[test]
+    fn intact_data_passes_the_checksum() {
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let result = result_for(&task()).with_checksum(&data);
+
+        assert!(result.verify_checksum(&data));
+    }
+
+    #[test]
+    fn a_flipped_byte_fails_the_checksum() {
+        let mut data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let result = result_for(&task()).with_checksum(&data);
+
+        data[3] ^= 0x01;
+
+        assert!(!result.verify_checksum(&data));
+    }
+
+    #[test]
+    fn a_fragment_is_painted_at_its_resolution() {
+        let palette = PaletteHandler::new();
+        let result = result_for(&task());
+        let mut counts = vec![0.0; 8 * 4];
+        counts[8 + 3] = 0.5;
+
+        let image = result.to_image(&counts, &palette);
+
+        let (r, g, b) = palette.calculate_color(0.5);
+        assert_eq!(image.dimensions(), (8, 4));
+        assert_eq!(image.get_pixel(3, 1), &Rgb([r, g, b]));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 0]));
+    }
+}
@@ -1,26 +1,107 @@
 use crate::models::{
     fractal::{fractal::Fractal, fractal_descriptor::FractalDescriptor},
-    pixel::{pixel_data::PixelData, pixel_intensity::PixelIntensity},
+    pixel::{pixel_data::PixelData, pixel_intensity::IntensityFormat},
     range::Range,
     resolution::Resolution,
     u8_data::U8Data,
 };
 use image::{ImageBuffer, Rgb};
-use log::{debug, error};
+use log::debug;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::{
+    cmp::Ordering,
+    fmt,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
 
 use super::{fragment::Fragment, fragment_result::FragmentResult};
 
 type FragmentResultData = Vec<u8>;
 
+/// Largest fragment a worker agrees to compute, a 4096x4096 tile.
+pub const DEFAULT_MAX_TASK_PIXELS: u64 = 4096 * 4096;
+
+/// Most samples per pixel along each axis, a pixel being computed up to 64 times.
+pub const MAX_SAMPLES_PER_PIXEL: u8 = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidTask {
+    EmptyResolution(Resolution),
+    TooManyPixels {
+        pixels: u64,
+        max: u64,
+    },
+    EmptyRange(Range),
+    NoIterations,
+    TooManySamples {
+        samples: u8,
+        max: u8,
+    },
+    /// A field left unset on a [`super::fragment_task_builder::FragmentTaskBuilder`].
+    MissingField(&'static str),
+}
+
+impl fmt::Display for InvalidTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidTask::EmptyResolution(resolution) => write!(
+                f,
+                "resolution {}x{} has no pixels",
+                resolution.nx, resolution.ny
+            ),
+            InvalidTask::TooManyPixels { pixels, max } => write!(
+                f,
+                "{} pixels exceed the maximum of {} pixels per task",
+                pixels, max
+            ),
+            InvalidTask::EmptyRange(range) => write!(
+                f,
+                "range ({}, {}) -> ({}, {}) must grow on both axes",
+                range.min.x, range.min.y, range.max.x, range.max.y
+            ),
+            InvalidTask::NoIterations => write!(f, "max_iteration must be positive"),
+            InvalidTask::TooManySamples { samples, max } => write!(
+                f,
+                "{} samples per pixel exceed the maximum of {}",
+                samples, max
+            ),
+            InvalidTask::MissingField(field) => write!(f, "{} must be set", field),
+        }
+    }
+}
+
+impl std::error::Error for InvalidTask {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FragmentTask {
     pub id: U8Data,
+    /// Unique to this task and echoed by its result, nil when the server predates it.
+    #[serde(default)]
+    pub task_uuid: Uuid,
     pub fractal: FractalDescriptor,
     pub max_iteration: u32,
     pub resolution: Resolution,
     pub range: Range,
+    #[serde(default = "default_samples_per_pixel")]
+    pub samples_per_pixel: u8,
+    /// Packing of the pixel intensities sent back, `f32` for peers that predate it.
+    #[serde(default)]
+    pub intensity_format: IntensityFormat,
+    /// Name the server registered the requesting worker under, which may differ from the
+    /// requested one when it was already taken.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assigned_name: Option<String>,
+}
+
+fn default_samples_per_pixel() -> u8 {
+    1
+}
+
+/// Scales an iteration count to the `[0, 1]` range the palettes expect, points that never
+/// escaped within `max_iteration` reaching 1.
+pub fn normalize_count(count: f64, max_iteration: u32) -> f64 {
+    count / max_iteration.max(1) as f64
 }
 
 impl FragmentTask {
@@ -33,22 +114,97 @@ impl FragmentTask {
     ) -> Self {
         Self {
             id,
+            task_uuid: Uuid::new_v4(),
             fractal,
             max_iteration,
             resolution,
             range,
+            samples_per_pixel: default_samples_per_pixel(),
+            intensity_format: IntensityFormat::default(),
+            assigned_name: None,
         }
     }
 
+    /// Checks that the task can be computed, with at most `max_pixels` pixels.
+    pub fn validate(&self, max_pixels: u64) -> Result<(), InvalidTask> {
+        let Resolution { nx, ny } = self.resolution;
+        if nx == 0 || ny == 0 {
+            return Err(InvalidTask::EmptyResolution(self.resolution));
+        }
+
+        let pixels = nx as u64 * ny as u64;
+        if pixels > max_pixels {
+            return Err(InvalidTask::TooManyPixels {
+                pixels,
+                max: max_pixels,
+            });
+        }
+
+        // NaN bounds compare as unordered and are rejected as well
+        let Range { min, max } = self.range;
+        if max.x.partial_cmp(&min.x) != Some(Ordering::Greater)
+            || max.y.partial_cmp(&min.y) != Some(Ordering::Greater)
+        {
+            return Err(InvalidTask::EmptyRange(self.range));
+        }
+
+        if self.max_iteration == 0 {
+            return Err(InvalidTask::NoIterations);
+        }
+
+        if self.samples_per_pixel > MAX_SAMPLES_PER_PIXEL {
+            return Err(InvalidTask::TooManySamples {
+                samples: self.samples_per_pixel,
+                max: MAX_SAMPLES_PER_PIXEL,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Lowers `max_iteration` to `cap`, returning whether it was above it.
+    pub fn clamp_max_iteration(&mut self, cap: u32) -> bool {
+        let clamped = self.max_iteration > cap;
+        self.max_iteration = self.max_iteration.min(cap);
+        clamped
+    }
+
     pub fn perform(
         &self,
+    ) -> Result<(FragmentResult, FragmentResultData), Box<dyn std::error::Error>> {
+        self.perform_with(self.fractal.as_fractal())
+    }
+
+    /// Performs the task and reports how long it took, also stamped on the result.
+    pub fn perform_timed(
+        &self,
+    ) -> Result<(FragmentResult, FragmentResultData, Duration), Box<dyn std::error::Error>> {
+        self.perform_timed_with(self.fractal.as_fractal())
+    }
+
+    fn perform_timed_with(
+        &self,
+        fractal: &dyn Fractal,
+    ) -> Result<(FragmentResult, FragmentResultData, Duration), Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let (result, data) = self.perform_with(fractal)?;
+        let elapsed = start.elapsed();
+
+        Ok((result.with_compute_time(elapsed), data, elapsed))
+    }
+
+    fn perform_with(
+        &self,
+        fractal: &dyn Fractal,
     ) -> Result<(FragmentResult, FragmentResultData), Box<dyn std::error::Error>> {
         let (image_buffer, pixel_data) = self.initialize_buffers()?;
-        let data = self.calculate_pixels(image_buffer)?;
+        let data = self.calculate_pixels(image_buffer, fractal)?;
 
         debug!("Calculated pixels for FragmentTask ID: {:?}", self.id);
         let fragment_result =
-            FragmentResult::new(self.id.clone(), self.resolution, self.range, pixel_data);
+            FragmentResult::new(self.id.clone(), self.resolution, self.range, pixel_data)
+                .with_checksum(&data)
+                .for_task(self.task_uuid);
 
         Ok((fragment_result, data))
     }
@@ -68,60 +224,47 @@ impl FragmentTask {
     fn calculate_pixels(
         &self,
         image_buffer: ImageBuffer<Rgb<u8>, Vec<u8>>,
+        fractal: &dyn Fractal,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let mut data = Vec::new();
+        let mut data =
+            Vec::with_capacity(image_buffer.pixels().len() * self.intensity_format.pixel_size());
 
         for (x, y, _pixel) in image_buffer.enumerate_pixels() {
-            let (mapped_x, mapped_y) = self.map_coordinates(x, y);
-            let (zn, count) = self.calculate_fractal(mapped_x, mapped_y);
-
-            let pixel_intensity =
-                PixelIntensity::new(zn as f32, (count as f32) / self.max_iteration as f32);
-
-            data.write_all(&pixel_intensity.zn.to_be_bytes())
-                .map_err(|e| {
-                    error!("Failed to write zn to pixel data: {}", e);
-                    e
-                })?;
-            data.write_all(&pixel_intensity.count.to_be_bytes())
-                .map_err(|e| {
-                    error!("Failed to write count to pixel data: {}", e);
-                    e
-                })?;
+            let (zn, count) = self.sample_pixel(fractal, x, y);
+            self.intensity_format
+                .encode(zn, normalize_count(count, self.max_iteration), &mut data);
         }
 
         Ok(data)
     }
 
-    fn map_coordinates(&self, x: u32, y: u32) -> (f64, f64) {
-        let Range { min, max } = &self.range;
-        let mapped_x = min.x + (x as f64 / self.resolution.nx as f64) * (max.x - min.x);
-        let mapped_y = min.y + (y as f64 / self.resolution.ny as f64) * (max.y - min.y);
-        (mapped_x, mapped_y)
-    }
-
-    fn calculate_fractal(&self, x: f64, y: f64) -> (f64, f64) {
-        match &self.fractal {
-            FractalDescriptor::Julia(julia) => julia.generate(self.max_iteration, x, y),
-            FractalDescriptor::Mandelbrot(mandelbrot) => {
-                mandelbrot.generate(self.max_iteration, x, y)
-            }
-            FractalDescriptor::IteratedSinZ(iterated_sin_z) => {
-                iterated_sin_z.generate(self.max_iteration, x, y)
-            }
-            FractalDescriptor::NewtonRaphsonZ3(newton_raphson_3) => {
-                newton_raphson_3.generate(self.max_iteration, x, y)
-            }
-            FractalDescriptor::NewtonRaphsonZ4(newton_raphson_4) => {
-                newton_raphson_4.generate(self.max_iteration, x, y)
-            }
-            FractalDescriptor::NovaNewtonRapshonZ3(nova_newton_raphson) => {
-                nova_newton_raphson.generate(self.max_iteration, x, y)
-            }
-            FractalDescriptor::NovaNewtonRapshonZ4(nova_newton_raphson) => {
-                nova_newton_raphson.generate(self.max_iteration, x, y)
+    // average a grid of samples_per_pixel x samples_per_pixel sub-samples to smooth out edges,
+    // each at the center of its cell so a single sample lands on the center of the pixel
+    fn sample_pixel(&self, fractal: &dyn Fractal, x: u32, y: u32) -> (f64, f64) {
+        let samples = self.samples_per_pixel.max(1) as u32;
+        let step = 1.0 / samples as f64;
+        let (mut zn_sum, mut count_sum) = (0.0, 0.0);
+        for sy in 0..samples {
+            for sx in 0..samples {
+                let (mapped_x, mapped_y) = self.map_coordinates(
+                    x as f64 + (sx as f64 + 0.5) * step,
+                    y as f64 + (sy as f64 + 0.5) * step,
+                );
+                let (zn, count) = fractal.generate(self.max_iteration, mapped_x, mapped_y);
+                zn_sum += zn;
+                count_sum += count;
             }
         }
+
+        let total = (samples * samples) as f64;
+        (zn_sum / total, count_sum / total)
+    }
+
+    fn map_coordinates(&self, x: f64, y: f64) -> (f64, f64) {
+        let Range { min, max } = &self.range;
+        let mapped_x = min.x + (x / self.resolution.nx as f64) * (max.x - min.x);
+        let mapped_y = min.y + (y / self.resolution.ny as f64) * (max.y - min.y);
+        (mapped_x, mapped_y)
     }
 }
 
@@ -133,6 +276,191 @@ impl Fragment for FragmentTask {
 
     fn from_json(fragment: &str) -> Result<Self, serde_json::Error> {
         let v: serde_json::Value = serde_json::from_str(fragment)?;
-        serde_json::from_value(v["FragmentTask"].clone())
+        let task: FragmentTask = serde_json::from_value(v["FragmentTask"].clone())?;
+        task.validate(DEFAULT_MAX_TASK_PIXELS)
+            .map_err(serde::de::Error::custom)?;
+        Ok(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{fractal::mandelbrot::Mandelbrot, point::Point};
+
+    use super::*;
+
+    fn task(resolution: Resolution, range: Range) -> FragmentTask {
+        FragmentTask::new(
+            U8Data::new(0, 16),
+            FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            64,
+            resolution,
+            range,
+        )
+    }
+
+    fn unit_range() -> Range {
+        Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0))
+    }
+
+    fn round_trip(task: &FragmentTask) -> Result<FragmentTask, serde_json::Error> {
+        FragmentTask::from_json(&task.to_json().unwrap().to_string())
+    }
+
+    #[test]
+    fn valid_tasks_are_accepted() {
+        let task = task(Resolution::new(100, 50), unit_range());
+
+        assert_eq!(task.validate(DEFAULT_MAX_TASK_PIXELS), Ok(()));
+        assert!(round_trip(&task).is_ok());
+    }
+
+    #[test]
+    fn counts_are_normalized_by_the_iteration_budget() {
+        assert_eq!(normalize_count(64.0, 64), 1.0);
+        assert_eq!(normalize_count(32.0, 64), 0.5);
+        assert_eq!(normalize_count(0.0, 64), 0.0);
+    }
+
+    #[test]
+    fn performed_tasks_send_normalized_counts() {
+        // the Mandelbrot interior never escapes, the left pixel centered on -2.25 escapes
+        // right away
+        let task = task(
+            Resolution::new(2, 1),
+            Range::new(Point::new(-3.0, -0.01), Point::new(0.0, 0.01)),
+        );
+
+        let (_, data) = task.perform().unwrap();
+        let counts = task.intensity_format.counts(&data);
+
+        assert!(counts[0] < 0.1, "{:?}", counts);
+        assert_eq!(counts[1], 1.0);
+    }
+
+    #[test]
+    fn zero_resolution_is_rejected() {
+        let task = task(Resolution::new(0, 50), unit_range());
+
+        assert!(matches!(
+            task.validate(DEFAULT_MAX_TASK_PIXELS),
+            Err(InvalidTask::EmptyResolution(_))
+        ));
+        let error = round_trip(&task).unwrap_err().to_string();
+        assert!(error.contains("has no pixels"), "{}", error);
+    }
+
+    #[test]
+    fn inverted_ranges_are_rejected() {
+        let inverted_x = Range::new(Point::new(1.0, -1.0), Point::new(-1.0, 1.0));
+        let flat_y = Range::new(Point::new(-1.0, 1.0), Point::new(1.0, 1.0));
+        let nan = Range::new(Point::new(f64::NAN, -1.0), Point::new(1.0, 1.0));
+
+        for range in [inverted_x, flat_y, nan] {
+            let task = task(Resolution::new(10, 10), range);
+            assert!(matches!(
+                task.validate(DEFAULT_MAX_TASK_PIXELS),
+                Err(InvalidTask::EmptyRange(_))
+            ));
+        }
+    }
+
+    // sleeps on every pixel so the computation takes a known time
+    struct SlowFractal(Duration);
+
+    impl Fractal for SlowFractal {
+        fn generate(&self, _max_iterations: u32, _x: f64, _y: f64) -> (f64, f64) {
+            std::thread::sleep(self.0);
+            (0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn timed_tasks_report_their_compute_time() {
+        let task = task(Resolution::new(2, 2), unit_range());
+        let slow = SlowFractal(Duration::from_millis(10));
+
+        let (result, _, elapsed) = task.perform_timed_with(&slow).unwrap();
+
+        assert!(elapsed >= Duration::from_millis(40));
+        assert!(elapsed < Duration::from_secs(1));
+        assert_eq!(result.compute_ms, Some(elapsed.as_millis() as u32));
+    }
+
+    fn variance(values: &[f64]) -> f64 {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+    }
+
+    #[test]
+    fn supersampling_smooths_out_a_high_contrast_region() {
+        // straddles the edge of the Mandelbrot set, where neighbouring pixels differ the most
+        let mut task = task(
+            Resolution::new(32, 32),
+            Range::new(Point::new(-0.80, 0.05), Point::new(-0.70, 0.15)),
+        );
+        task.max_iteration = 256;
+
+        let (_, data) = task.perform().unwrap();
+        let single = variance(&task.intensity_format.counts(&data));
+        task.samples_per_pixel = 4;
+        let (_, data) = task.perform().unwrap();
+        let supersampled = variance(&task.intensity_format.counts(&data));
+
+        assert!(supersampled < single, "{} >= {}", supersampled, single);
+    }
+
+    // hands back the point it is sampled at
+    struct Coordinates;
+
+    impl Fractal for Coordinates {
+        fn generate(&self, _max_iterations: u32, x: f64, y: f64) -> (f64, f64) {
+            (x, y)
+        }
+    }
+
+    #[test]
+    fn pixels_are_sampled_around_their_center_at_any_rate() {
+        let mut task = task(Resolution::new(2, 2), unit_range());
+
+        for samples in [1, 2, 4] {
+            task.samples_per_pixel = samples;
+            let (x, y) = task.sample_pixel(&Coordinates, 1, 0);
+
+            assert!((x - 0.5).abs() < 1e-12, "{} samples: x = {}", samples, x);
+            assert!((y + 0.5).abs() < 1e-12, "{} samples: y = {}", samples, y);
+        }
+    }
+
+    #[test]
+    fn too_many_samples_per_pixel_are_rejected() {
+        let mut task = task(Resolution::new(10, 10), unit_range());
+        task.samples_per_pixel = MAX_SAMPLES_PER_PIXEL;
+        assert_eq!(task.validate(DEFAULT_MAX_TASK_PIXELS), Ok(()));
+
+        task.samples_per_pixel = u8::MAX;
+        assert_eq!(
+            task.validate(DEFAULT_MAX_TASK_PIXELS),
+            Err(InvalidTask::TooManySamples {
+                samples: u8::MAX,
+                max: MAX_SAMPLES_PER_PIXEL,
+            })
+        );
+        assert!(round_trip(&task).is_err());
+    }
+
+    #[test]
+    fn oversized_resolutions_are_rejected() {
+        let task = task(Resolution::new(u16::MAX, u16::MAX), unit_range());
+
+        assert_eq!(
+            task.validate(DEFAULT_MAX_TASK_PIXELS),
+            Err(InvalidTask::TooManyPixels {
+                pixels: u16::MAX as u64 * u16::MAX as u64,
+                max: DEFAULT_MAX_TASK_PIXELS,
+            })
+        );
+        assert!(round_trip(&task).is_err());
+        assert_eq!(task.validate(u64::MAX), Ok(()));
     }
 }
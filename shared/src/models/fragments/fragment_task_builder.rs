@@ -0,0 +1,202 @@
+use crate::{
+    models::{
+        fractal::fractal_descriptor::FractalDescriptor, pixel::pixel_intensity::IntensityFormat,
+        range::Range, resolution::Resolution, u8_data::U8Data,
+    },
+    networking::DEFAULT_MAX_ITERATION,
+};
+
+use super::fragment_task::{FragmentTask, InvalidTask, DEFAULT_MAX_TASK_PIXELS};
+
+/// Assembles a [`FragmentTask`] and validates it on [`FragmentTaskBuilder::build`].
+///
+/// The fractal, resolution and range have to be set, the rest falls back to the defaults of
+/// [`FragmentTask::new`] with [`DEFAULT_MAX_ITERATION`] iterations.
+#[derive(Debug, Clone)]
+pub struct FragmentTaskBuilder {
+    id: U8Data,
+    fractal: Option<FractalDescriptor>,
+    max_iteration: u32,
+    resolution: Option<Resolution>,
+    range: Option<Range>,
+    samples_per_pixel: Option<u8>,
+    intensity_format: IntensityFormat,
+    max_pixels: u64,
+}
+
+impl Default for FragmentTaskBuilder {
+    fn default() -> Self {
+        Self {
+            id: U8Data::new(0, 16),
+            fractal: None,
+            max_iteration: DEFAULT_MAX_ITERATION,
+            resolution: None,
+            range: None,
+            samples_per_pixel: None,
+            intensity_format: IntensityFormat::default(),
+            max_pixels: DEFAULT_MAX_TASK_PIXELS,
+        }
+    }
+}
+
+impl FragmentTask {
+    pub fn builder() -> FragmentTaskBuilder {
+        FragmentTaskBuilder::default()
+    }
+}
+
+impl FragmentTaskBuilder {
+    pub fn id(mut self, id: U8Data) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn fractal(mut self, fractal: FractalDescriptor) -> Self {
+        self.fractal = Some(fractal);
+        self
+    }
+
+    pub fn max_iteration(mut self, max_iteration: u32) -> Self {
+        self.max_iteration = max_iteration;
+        self
+    }
+
+    pub fn resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn range(mut self, range: Range) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u8) -> Self {
+        self.samples_per_pixel = Some(samples_per_pixel);
+        self
+    }
+
+    pub fn intensity_format(mut self, intensity_format: IntensityFormat) -> Self {
+        self.intensity_format = intensity_format;
+        self
+    }
+
+    /// Most pixels the task may hold, [`DEFAULT_MAX_TASK_PIXELS`] unless set.
+    pub fn max_pixels(mut self, max_pixels: u64) -> Self {
+        self.max_pixels = max_pixels;
+        self
+    }
+
+    pub fn build(self) -> Result<FragmentTask, InvalidTask> {
+        let fractal = self.fractal.ok_or(InvalidTask::MissingField("fractal"))?;
+        let resolution = self
+            .resolution
+            .ok_or(InvalidTask::MissingField("resolution"))?;
+        let range = self.range.ok_or(InvalidTask::MissingField("range"))?;
+
+        let mut task = FragmentTask::new(self.id, fractal, self.max_iteration, resolution, range);
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            task.samples_per_pixel = samples_per_pixel;
+        }
+        task.intensity_format = self.intensity_format;
+
+        task.validate(self.max_pixels)?;
+        Ok(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{fractal::mandelbrot::Mandelbrot, point::Point};
+
+    use super::*;
+
+    fn builder() -> FragmentTaskBuilder {
+        FragmentTask::builder()
+            .fractal(FractalDescriptor::Mandelbrot(Mandelbrot::new()))
+            .resolution(Resolution::new(100, 50))
+            .range(Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)))
+    }
+
+    #[test]
+    fn a_complete_builder_builds_the_task() {
+        let task = builder()
+            .max_iteration(512)
+            .samples_per_pixel(4)
+            .intensity_format(IntensityFormat::F64)
+            .build()
+            .unwrap();
+
+        assert_eq!(task.max_iteration, 512);
+        assert_eq!(task.resolution, Resolution::new(100, 50));
+        assert_eq!(task.samples_per_pixel, 4);
+        assert_eq!(task.intensity_format, IntensityFormat::F64);
+        assert_eq!(task.id.count, 16);
+    }
+
+    #[test]
+    fn unset_fields_are_reported() {
+        let range = Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0));
+
+        assert_eq!(
+            FragmentTask::builder()
+                .resolution(Resolution::new(1, 1))
+                .range(range)
+                .build()
+                .unwrap_err(),
+            InvalidTask::MissingField("fractal")
+        );
+        assert_eq!(
+            FragmentTask::builder()
+                .fractal(FractalDescriptor::Mandelbrot(Mandelbrot::new()))
+                .range(range)
+                .build()
+                .unwrap_err(),
+            InvalidTask::MissingField("resolution")
+        );
+        assert_eq!(
+            FragmentTask::builder()
+                .fractal(FractalDescriptor::Mandelbrot(Mandelbrot::new()))
+                .resolution(Resolution::new(1, 1))
+                .build()
+                .unwrap_err(),
+            InvalidTask::MissingField("range")
+        );
+    }
+
+    #[test]
+    fn a_resolution_without_pixels_is_rejected() {
+        let result = builder().resolution(Resolution::new(0, 50)).build();
+
+        assert!(matches!(result, Err(InvalidTask::EmptyResolution(_))));
+    }
+
+    #[test]
+    fn a_resolution_over_the_pixel_budget_is_rejected() {
+        let result = builder().max_pixels(100 * 50 - 1).build();
+
+        assert_eq!(
+            result.unwrap_err(),
+            InvalidTask::TooManyPixels {
+                pixels: 100 * 50,
+                max: 100 * 50 - 1
+            }
+        );
+    }
+
+    #[test]
+    fn an_empty_range_is_rejected() {
+        let result = builder()
+            .range(Range::new(Point::new(1.0, -1.0), Point::new(1.0, 1.0)))
+            .build();
+
+        assert!(matches!(result, Err(InvalidTask::EmptyRange(_))));
+    }
+
+    #[test]
+    fn zero_iterations_are_rejected() {
+        let result = builder().max_iteration(0).build();
+
+        assert_eq!(result.unwrap_err(), InvalidTask::NoIterations);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use super::fragment::Fragment;
+
+/// Sent by a worker while it computes a task, so the server knows it is still alive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub worker_name: String,
+}
+
+impl Heartbeat {
+    pub fn new(worker_name: String) -> Self {
+        Self { worker_name }
+    }
+}
+
+impl Fragment for Heartbeat {
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        let wrapped = serde_json::json!({ "Heartbeat": self });
+        serde_json::to_value(&wrapped)
+    }
+
+    fn from_json(fragment: &str) -> Result<Self, serde_json::Error> {
+        let v: serde_json::Value = serde_json::from_str(fragment)?;
+        serde_json::from_value(v["Heartbeat"].clone())
+    }
+}
@@ -1,4 +1,7 @@
+pub mod error_message;
 pub mod fragment;
 pub mod fragment_request;
 pub mod fragment_result;
 pub mod fragment_task;
+pub mod fragment_task_builder;
+pub mod heartbeat;
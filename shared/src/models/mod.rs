@@ -4,4 +4,5 @@ pub mod pixel;
 pub mod point;
 pub mod range;
 pub mod resolution;
+pub mod tile;
 pub mod u8_data;
@@ -1,8 +1,10 @@
+use std::{fmt, str::FromStr};
+
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncReadExt;
 
 use crate::networking::result::NetworkingResult;
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PixelIntensity {
     pub zn: f32,
     pub count: f32,
@@ -27,3 +29,151 @@ impl PixelIntensity {
         Ok(Self { zn, count })
     }
 }
+
+/// How the pixel intensities of a fragment are packed, always big endian.
+///
+/// `F32` is the historical `(zn, count)` pair of [`PixelIntensity`]. `F64` keeps the full
+/// precision for deep zooms, `U16` only sends the normalized count, the only value colored,
+/// scaled to the whole `u16` range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntensityFormat {
+    U16,
+    #[default]
+    F32,
+    F64,
+}
+
+impl IntensityFormat {
+    /// Bytes taken by a single pixel.
+    pub fn pixel_size(self) -> usize {
+        match self {
+            IntensityFormat::U16 => 2,
+            IntensityFormat::F32 => 8,
+            IntensityFormat::F64 => 16,
+        }
+    }
+
+    pub fn encode(self, zn: f64, count: f64, data: &mut Vec<u8>) {
+        match self {
+            IntensityFormat::U16 => {
+                let count = (count.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16;
+                data.extend_from_slice(&count.to_be_bytes());
+            }
+            IntensityFormat::F32 => {
+                data.extend_from_slice(&(zn as f32).to_be_bytes());
+                data.extend_from_slice(&(count as f32).to_be_bytes());
+            }
+            IntensityFormat::F64 => {
+                data.extend_from_slice(&zn.to_be_bytes());
+                data.extend_from_slice(&count.to_be_bytes());
+            }
+        }
+    }
+
+    /// Reads back the `(zn, count)` pair of a pixel, `zn` is 0 for `U16`.
+    ///
+    /// `pixel` must hold exactly [`IntensityFormat::pixel_size`] bytes.
+    pub fn decode(self, pixel: &[u8]) -> (f64, f64) {
+        match self {
+            IntensityFormat::U16 => (
+                0.0,
+                u16::from_be_bytes(array(pixel)) as f64 / u16::MAX as f64,
+            ),
+            IntensityFormat::F32 => (
+                f32::from_be_bytes(array(&pixel[..4])) as f64,
+                f32::from_be_bytes(array(&pixel[4..])) as f64,
+            ),
+            IntensityFormat::F64 => (
+                f64::from_be_bytes(array(&pixel[..8])),
+                f64::from_be_bytes(array(&pixel[8..])),
+            ),
+        }
+    }
+
+    /// The normalized count of every pixel in `data`.
+    pub fn counts(self, data: &[u8]) -> Vec<f64> {
+        data.chunks_exact(self.pixel_size())
+            .map(|pixel| self.decode(pixel).1)
+            .collect()
+    }
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes.try_into().expect("pixel of the wrong size")
+}
+
+impl fmt::Display for IntensityFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntensityFormat::U16 => write!(f, "u16"),
+            IntensityFormat::F32 => write!(f, "f32"),
+            IntensityFormat::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+impl FromStr for IntensityFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "u16" => Ok(IntensityFormat::U16),
+            "f32" => Ok(IntensityFormat::F32),
+            "f64" => Ok(IntensityFormat::F64),
+            _ => Err(format!("unknown intensity format: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(format: IntensityFormat, zn: f64, count: f64) -> Vec<u8> {
+        let mut data = Vec::new();
+        format.encode(zn, count, &mut data);
+        data
+    }
+
+    #[test]
+    fn u16_keeps_only_the_scaled_count() {
+        let data = encoded(IntensityFormat::U16, 3.5, 0.5);
+
+        assert_eq!(data, 32768u16.to_be_bytes());
+        let (zn, count) = IntensityFormat::U16.decode(&data);
+        assert_eq!(zn, 0.0);
+        assert!((count - 0.5).abs() <= 1.0 / u16::MAX as f64);
+    }
+
+    #[test]
+    fn f32_packs_the_historical_pair() {
+        let data = encoded(IntensityFormat::F32, 3.5, 0.25);
+
+        assert_eq!(data, [3.5f32.to_be_bytes(), 0.25f32.to_be_bytes()].concat());
+        assert_eq!(IntensityFormat::F32.decode(&data), (3.5, 0.25));
+    }
+
+    #[test]
+    fn f64_keeps_the_full_precision() {
+        let count = 1.0 / 3.0;
+        let data = encoded(IntensityFormat::F64, 3.5, count);
+
+        assert_eq!(data, [3.5f64.to_be_bytes(), count.to_be_bytes()].concat());
+        assert_eq!(IntensityFormat::F64.decode(&data), (3.5, count));
+    }
+
+    #[test]
+    fn every_format_takes_its_pixel_size() {
+        for format in [
+            IntensityFormat::U16,
+            IntensityFormat::F32,
+            IntensityFormat::F64,
+        ] {
+            let data = [encoded(format, 1.0, 0.5), encoded(format, 2.0, 1.0)].concat();
+
+            assert_eq!(data.len(), 2 * format.pixel_size(), "{}", format);
+            assert_eq!(format.counts(&data).len(), 2, "{}", format);
+        }
+    }
+}
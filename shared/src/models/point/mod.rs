@@ -1,5 +1,7 @@
+use std::ops::{Add, Sub};
+
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -9,4 +11,60 @@ impl Point {
     pub fn new(x: f64, y: f64) -> Self {
         Self { x, y }
     }
+
+    pub fn scale(self, factor: f64) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+
+    /// Euclidean distance to `other`.
+    pub fn distance(&self, other: &Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_add_and_subtract_component_wise() {
+        let a = Point::new(1.5, -2.0);
+        let b = Point::new(0.5, 4.0);
+
+        assert_eq!(a + b, Point::new(2.0, 2.0));
+        assert_eq!(a - b, Point::new(1.0, -6.0));
+        assert_eq!(Point::add(a, b) - b, a);
+    }
+
+    #[test]
+    fn scaling_multiplies_both_coordinates() {
+        assert_eq!(Point::new(1.5, -2.0).scale(2.0), Point::new(3.0, -4.0));
+        assert_eq!(Point::new(1.5, -2.0).scale(0.0), Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn distance_is_euclidean() {
+        let origin = Point::new(0.0, 0.0);
+        let point = Point::new(3.0, 4.0);
+
+        assert_eq!(origin.distance(&point), 5.0);
+        assert_eq!(point.distance(&origin), 5.0);
+        assert_eq!(point.distance(&point), 0.0);
+    }
 }
@@ -1,7 +1,7 @@
 use super::point::Point;
 
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Range {
     pub min: Point,
     pub max: Point,
@@ -11,4 +11,117 @@ impl Range {
     pub fn new(min: Point, max: Point) -> Self {
         Self { min, max }
     }
+
+    pub fn center(&self) -> Point {
+        Point::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+        )
+    }
+
+    /// Cuts the range into a `cols` x `rows` grid of equal cells, row by row. The last
+    /// column and row end exactly on `max` so the cells cover the range without gaps.
+    pub fn subdivide(&self, cols: u32, rows: u32) -> Vec<Range> {
+        let step_x = (self.max.x - self.min.x) / cols as f64;
+        let step_y = (self.max.y - self.min.y) / rows as f64;
+        let x = |column: u32| match column {
+            column if column == cols => self.max.x,
+            column => self.min.x + column as f64 * step_x,
+        };
+        let y = |row: u32| match row {
+            row if row == rows => self.max.y,
+            row => self.min.y + row as f64 * step_y,
+        };
+
+        (0..rows)
+            .flat_map(|row| {
+                (0..cols).map(move |column| {
+                    Range::new(
+                        Point::new(x(column), y(row)),
+                        Point::new(x(column + 1), y(row + 1)),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Whether `point` lies within the range, edges included.
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    /// Whether the two ranges share some area, ranges that only touch along an edge do not.
+    pub fn intersects(&self, other: &Range) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Range {
+        Range::new(Point::new(min_x, min_y), Point::new(max_x, max_y))
+    }
+
+    #[test]
+    fn subdivision_covers_the_range_with_a_cell_per_grid_slot() {
+        let whole = range(-1.2, -1.2, 1.2, 1.2);
+
+        let cells = whole.subdivide(3, 2);
+
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[0].min, whole.min);
+        assert_eq!(cells[5].max, whole.max);
+        let area: f64 = cells
+            .iter()
+            .map(|cell| (cell.max.x - cell.min.x) * (cell.max.y - cell.min.y))
+            .sum();
+        assert!((area - 2.4 * 2.4).abs() < 1e-12);
+        for (index, a) in cells.iter().enumerate() {
+            for b in &cells[index + 1..] {
+                assert!(!a.intersects(b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn subdivision_is_row_by_row() {
+        let cells = range(0.0, 0.0, 2.0, 2.0).subdivide(2, 2);
+
+        assert_eq!(cells[1], range(1.0, 0.0, 2.0, 1.0));
+        assert_eq!(cells[2], range(0.0, 1.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn the_center_is_contained() {
+        let range = range(-2.0, 0.0, 1.0, 4.0);
+
+        assert_eq!(range.center(), Point::new(-0.5, 2.0));
+        assert!(range.contains(range.center()));
+        assert!(range.contains(range.max));
+        assert!(!range.contains(Point::new(1.5, 2.0)));
+    }
+
+    #[test]
+    fn overlapping_ranges_intersect() {
+        let a = range(0.0, 0.0, 2.0, 2.0);
+        let b = range(1.0, 1.0, 3.0, 3.0);
+
+        assert!(a.intersects(&b));
+        assert!(b.intersects(&a));
+        assert!(a.intersects(&range(0.5, 0.5, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn disjoint_and_adjacent_ranges_do_not_intersect() {
+        let a = range(0.0, 0.0, 1.0, 1.0);
+
+        assert!(!a.intersects(&range(2.0, 2.0, 3.0, 3.0)));
+        assert!(!a.intersects(&range(0.0, 2.0, 1.0, 3.0)));
+        assert!(!a.intersects(&range(1.0, 0.0, 2.0, 1.0)));
+    }
 }
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Resolution {
     pub nx: u16,
     pub ny: u16,
@@ -9,4 +9,46 @@ impl Resolution {
     pub fn new(nx: u16, ny: u16) -> Self {
         Self { nx, ny }
     }
+
+    /// Like [`Resolution::new`], `None` when either dimension is zero.
+    pub fn checked(nx: u16, ny: u16) -> Option<Self> {
+        (nx > 0 && ny > 0).then_some(Self { nx, ny })
+    }
+
+    /// Pixels covered, widened before multiplying so large resolutions do not overflow.
+    pub fn pixel_count(&self) -> usize {
+        self.nx as usize * self.ny as usize
+    }
+
+    /// Width over height, NaN or infinite when a dimension is zero.
+    pub fn aspect_ratio(&self) -> f64 {
+        self.nx as f64 / self.ny as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_count_does_not_overflow() {
+        let resolution = Resolution::new(u16::MAX, u16::MAX);
+
+        assert_eq!(resolution.pixel_count(), 65535 * 65535);
+        assert!(resolution.nx.checked_mul(resolution.ny).is_none());
+        assert_eq!(Resolution::new(300, 200).pixel_count(), 60_000);
+    }
+
+    #[test]
+    fn aspect_ratio_is_width_over_height() {
+        assert_eq!(Resolution::new(1920, 1080).aspect_ratio(), 16.0 / 9.0);
+        assert_eq!(Resolution::new(300, 300).aspect_ratio(), 1.0);
+    }
+
+    #[test]
+    fn checked_resolutions_have_pixels() {
+        assert_eq!(Resolution::checked(4, 3), Some(Resolution::new(4, 3)));
+        assert_eq!(Resolution::checked(0, 3), None);
+        assert_eq!(Resolution::checked(4, 0), None);
+    }
 }
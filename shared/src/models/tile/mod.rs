@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::{point::Point, range::Range, resolution::Resolution};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Tile {
+    pub index: u32,
+    pub range: Range,
+    pub resolution: Resolution,
+}
+
+impl Tile {
+    pub fn new(index: u32, range: Range, resolution: Resolution) -> Self {
+        Self {
+            index,
+            range,
+            resolution,
+        }
+    }
+
+    /// Cuts the tile at `index` out of a `tiles` x `tiles` grid laid over a
+    /// `width` x `height` canvas viewing `range`.
+    ///
+    /// Tile edges are snapped to whole pixels so that the tiles cover the canvas
+    /// exactly, the last row and column absorbing any remainder.
+    pub fn from_grid(index: u32, tiles: u32, width: u32, height: u32, range: &Range) -> Self {
+        let column = index % tiles;
+        let row = index / tiles;
+
+        let (x0, x1) = (column * width / tiles, (column + 1) * width / tiles);
+        let (y0, y1) = (row * height / tiles, (row + 1) * height / tiles);
+
+        let span_x = range.max.x - range.min.x;
+        let span_y = range.max.y - range.min.y;
+        let to_x = |px: u32| range.min.x + px as f64 / width as f64 * span_x;
+        let to_y = |py: u32| range.min.y + py as f64 / height as f64 * span_y;
+
+        let range = Range::new(
+            Point::new(to_x(x0), to_y(y0)),
+            Point::new(to_x(x1), to_y(y1)),
+        );
+        let resolution = Resolution::new((x1 - x0) as u16, (y1 - y0) as u16);
+
+        Self::new(index, range, resolution)
+    }
+}
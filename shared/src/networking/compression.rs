@@ -0,0 +1,157 @@
+use std::{
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+
+use super::{error::NetworkingError, result::NetworkingResult};
+
+/// Codec applied to the binary section of a frame.
+///
+/// The codec travels in the most significant byte of the JSON length prefix, which is
+/// always zero for frames sent by peers unaware of compression.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    #[default]
+    None = 0,
+    Gzip = 1,
+    Zstd = 2,
+}
+
+const CODEC_SHIFT: u32 = 24;
+const LENGTH_MASK: u32 = (1 << CODEC_SHIFT) - 1;
+
+impl Compression {
+    pub fn from_u8(value: u8) -> NetworkingResult<Self> {
+        match value {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Gzip),
+            2 => Ok(Compression::Zstd),
+            _ => Err(NetworkingError::MalformedFrame(format!(
+                "unknown compression codec {}",
+                value
+            ))),
+        }
+    }
+
+    /// Packs the codec into the JSON length prefix.
+    pub fn encode_json_length(self, json_length: u32) -> NetworkingResult<u32> {
+        if json_length > LENGTH_MASK {
+            return Err(NetworkingError::MessageTooLarge {
+                length: json_length,
+                max: LENGTH_MASK,
+            });
+        }
+        Ok(((self as u32) << CODEC_SHIFT) | json_length)
+    }
+
+    /// Splits a JSON length prefix into its codec and the actual JSON length.
+    pub fn decode_json_length(prefix: u32) -> NetworkingResult<(Self, u32)> {
+        let codec = Compression::from_u8((prefix >> CODEC_SHIFT) as u8)?;
+        Ok((codec, prefix & LENGTH_MASK))
+    }
+
+    pub fn compress(self, data: &[u8]) -> NetworkingResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            Compression::Zstd => Ok(zstd::encode_all(data, 0)?),
+        }
+    }
+
+    /// Inflates `data`, refusing to produce more than `max_size` bytes.
+    pub fn decompress(self, data: &[u8], max_size: u32) -> NetworkingResult<Vec<u8>> {
+        let mut decompressed = Vec::new();
+        let limit = max_size as u64 + 1;
+        match self {
+            Compression::None => return Ok(data.to_vec()),
+            Compression::Gzip => {
+                GzDecoder::new(data)
+                    .take(limit)
+                    .read_to_end(&mut decompressed)?;
+            }
+            Compression::Zstd => {
+                zstd::Decoder::new(data)?
+                    .take(limit)
+                    .read_to_end(&mut decompressed)?;
+            }
+        }
+
+        if decompressed.len() > max_size as usize {
+            return Err(NetworkingError::MessageTooLarge {
+                length: u32::try_from(decompressed.len()).unwrap_or(u32::MAX),
+                max: max_size,
+            });
+        }
+        Ok(decompressed)
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression codec: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intensity_buffer() -> Vec<u8> {
+        (0..300 * 300)
+            .flat_map(|i: u32| {
+                let zn = (i % 300) as f32 / 300.0;
+                let count = (i / 300) as f32 / 300.0;
+                [zn.to_be_bytes(), count.to_be_bytes()].concat()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_an_intensity_buffer() {
+        let data = intensity_buffer();
+
+        for codec in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            let compressed = codec.compress(&data).unwrap();
+            let decompressed = codec.decompress(&compressed, data.len() as u32).unwrap();
+            assert_eq!(decompressed, data, "{} did not round trip", codec);
+        }
+    }
+
+    #[test]
+    fn json_length_prefix_carries_the_codec() {
+        let prefix = Compression::Zstd.encode_json_length(1234).unwrap();
+        let (codec, length) = Compression::decode_json_length(prefix).unwrap();
+
+        assert_eq!((codec, length), (Compression::Zstd, 1234));
+        assert_eq!(
+            Compression::decode_json_length(1234).unwrap(),
+            (Compression::None, 1234)
+        );
+    }
+}
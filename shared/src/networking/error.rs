@@ -1,44 +1,121 @@
-use std::fmt;
+use std::time::Duration;
 
-use colored::Colorize;
+use thiserror::Error;
 
-#[derive(Debug)]
+/// How much of an undecodable payload is kept in a [`NetworkingError::Serde`].
+pub const PAYLOAD_SNIPPET_LEN: usize = 200;
+
+#[derive(Debug, Error)]
 pub enum NetworkingError {
-    IoError(std::io::Error),
-    JsonError(serde_json::Error),
-    Error(Box<dyn std::error::Error>),
+    #[error("[IO Error] {0}")]
+    Io(#[from] std::io::Error),
+    #[error(
+        "[Serde Error] {source}{}",
+        payload.as_ref().map(|p| format!(" in `{}`", p)).unwrap_or_default()
+    )]
+    Serde {
+        source: serde_json::Error,
+        /// The start of the payload that failed to decode, if it came from a peer.
+        payload: Option<String>,
+    },
+    #[error("[Timeout] no data received for {0:?}")]
+    Timeout(Duration),
+    #[error("[Malformed Frame] {0}")]
+    MalformedFrame(String),
+    #[error(
+        "[Message Too Large] declared length of {length} bytes exceeds the maximum of {max} bytes"
+    )]
+    MessageTooLarge { length: u32, max: u32 },
+    #[error("[Unsupported Version] peer speaks protocol version {found}, expected {expected}")]
+    UnsupportedVersion { found: u8, expected: u8 },
+    #[error("[Signature Mismatch] payload signature mismatch")]
+    SignatureMismatch,
+    #[error("[TLS Error] {0}")]
+    Tls(String),
+    #[error("[General Error] {0}")]
+    Error(#[from] Box<dyn std::error::Error>),
 }
 
-impl fmt::Display for NetworkingError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            NetworkingError::IoError(err) => {
-                write!(f, "[{}] {}", "IO Error".red(), err)
-            }
-            NetworkingError::JsonError(err) => {
-                write!(f, "[{}] {}", "JSON Error".red(), err)
-            }
-            NetworkingError::Error(err) => {
-                write!(f, "[{}] {}", "General Error".red(), err)
-            }
+impl NetworkingError {
+    /// Wraps a failure to decode `payload`, keeping its first bytes for debugging.
+    pub fn serde(source: serde_json::Error, payload: &str) -> NetworkingError {
+        let mut end = payload.len().min(PAYLOAD_SNIPPET_LEN);
+        while !payload.is_char_boundary(end) {
+            end -= 1;
+        }
+        NetworkingError::Serde {
+            source,
+            payload: Some(payload[..end].to_string()),
         }
     }
 }
 
-impl From<std::io::Error> for NetworkingError {
-    fn from(err: std::io::Error) -> NetworkingError {
-        NetworkingError::IoError(err)
+impl From<serde_json::Error> for NetworkingError {
+    fn from(source: serde_json::Error) -> NetworkingError {
+        NetworkingError::Serde {
+            source,
+            payload: None,
+        }
     }
 }
 
-impl From<serde_json::Error> for NetworkingError {
-    fn from(err: serde_json::Error) -> NetworkingError {
-        NetworkingError::JsonError(err)
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn every_variant_names_its_cause() {
+        let serde_error = serde_json::from_str::<u32>("").unwrap_err();
+        let cases = [
+            (
+                NetworkingError::Io(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed")),
+                "[IO Error] pipe closed",
+            ),
+            (
+                NetworkingError::from(serde_error),
+                "[Serde Error] EOF while parsing a value at line 1 column 0",
+            ),
+            (
+                NetworkingError::Timeout(Duration::from_secs(30)),
+                "[Timeout] no data received for 30s",
+            ),
+            (
+                NetworkingError::MalformedFrame("truncated header".to_string()),
+                "[Malformed Frame] truncated header",
+            ),
+            (
+                NetworkingError::MessageTooLarge {
+                    length: 2048,
+                    max: 1024,
+                },
+                "[Message Too Large] declared length of 2048 bytes exceeds the maximum of 1024 bytes",
+            ),
+            (
+                NetworkingError::SignatureMismatch,
+                "[Signature Mismatch] payload signature mismatch",
+            ),
+            (
+                NetworkingError::Tls("no private key in server.key".to_string()),
+                "[TLS Error] no private key in server.key",
+            ),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(error.to_string(), expected);
+        }
     }
-}
 
-impl From<Box<dyn std::error::Error>> for NetworkingError {
-    fn from(err: Box<dyn std::error::Error>) -> NetworkingError {
-        NetworkingError::Error(err)
+    #[test]
+    fn malformed_payloads_are_quoted_in_the_error() {
+        let payload = format!(r#"{{"FragmentTask": {{"id": "{}"#, "x".repeat(300));
+        let source = serde_json::from_str::<serde_json::Value>(&payload).unwrap_err();
+
+        let error = NetworkingError::serde(source, &payload);
+
+        let message = error.to_string();
+        assert!(message.contains(&payload[..PAYLOAD_SNIPPET_LEN]));
+        assert!(!message.contains(&payload[..PAYLOAD_SNIPPET_LEN + 1]));
     }
 }
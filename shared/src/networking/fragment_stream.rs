@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use log::debug;
+use tokio::io::AsyncRead;
+
+use crate::{
+    env,
+    models::pixel::pixel_intensity::{IntensityFormat, PixelIntensity},
+};
+
+use super::{
+    binary_data_length, check_message_size,
+    compression::Compression,
+    error::NetworkingError,
+    read_binary_data, read_exact_with_timeout, read_json_message, read_message_length,
+    read_protocol_version,
+    result::NetworkingResult,
+    signature::{PayloadMac, SIGNATURE_SIZE},
+};
+
+/// Bytes read from the connection at a time while streaming a fragment.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The pixel intensities of a fragment result, handed out as they come off the connection.
+///
+/// Nothing vouches for them until the last one is read and [`FragmentStream::verify_signature`]
+/// holds, a caller drawing them early must be ready to discard what it drew.
+pub struct FragmentStream<'a, S> {
+    pub json_message: String,
+    stream: &'a mut S,
+    format: IntensityFormat,
+    timeout: Duration,
+    signature: Vec<u8>,
+    mac: PayloadMac,
+    // bytes of the frame still on the connection
+    remaining: usize,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+/// Reads the header of a fragment result frame, leaving its intensities to
+/// [`FragmentStream::next`]. Compressed data has to be inflated as a whole, it is read at once.
+pub async fn read_fragment_streaming<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    format: IntensityFormat,
+    max_message_size: u32,
+    timeout: Duration,
+) -> NetworkingResult<FragmentStream<'_, S>> {
+    read_protocol_version(stream, timeout).await?;
+    let message_length = read_message_length(stream, timeout).await?;
+    check_message_size(message_length, max_message_size)?;
+
+    let json_length_prefix = read_message_length(stream, timeout).await?;
+    let (compression, json_length) = Compression::decode_json_length(json_length_prefix)?;
+    check_message_size(json_length, max_message_size)?;
+    let data_length = binary_data_length(message_length, json_length)? as usize;
+
+    let json_message = read_json_message(stream, json_length as usize, timeout).await?;
+    let (remaining, chunk) = match compression {
+        Compression::None => (data_length, Vec::new()),
+        _ => {
+            let data = read_binary_data(stream, data_length, timeout).await?;
+            (0, compression.decompress(&data, max_message_size)?)
+        }
+    };
+    debug!(
+        "Streaming {} bytes of fragment data, compression: {}",
+        data_length, compression
+    );
+
+    let mac = PayloadMac::new(env::secret(), json_message.as_bytes());
+    let mut fragment = FragmentStream {
+        json_message,
+        stream,
+        format,
+        timeout,
+        signature: Vec::new(),
+        mac,
+        remaining,
+        chunk,
+        position: 0,
+    };
+    if !fragment.fill(SIGNATURE_SIZE).await? {
+        return Err(NetworkingError::MalformedFrame(
+            "fragment data is too short to hold a signature".to_string(),
+        ));
+    }
+    fragment.signature = fragment.chunk[..SIGNATURE_SIZE].to_vec();
+    fragment.position = SIGNATURE_SIZE;
+
+    Ok(fragment)
+}
+
+impl<S: AsyncRead + Unpin> FragmentStream<'_, S> {
+    /// The next pixel intensity, row by row, or `None` once the frame is exhausted.
+    pub async fn next(&mut self) -> Option<NetworkingResult<PixelIntensity>> {
+        let size = self.format.pixel_size();
+        match self.fill(size).await {
+            Ok(true) => {}
+            Ok(false) if self.position == self.chunk.len() => return None,
+            Ok(false) => {
+                return Some(Err(NetworkingError::MalformedFrame(
+                    "fragment data ends in the middle of a pixel intensity".to_string(),
+                )))
+            }
+            Err(e) => return Some(Err(e)),
+        }
+
+        let pixel = &self.chunk[self.position..self.position + size];
+        self.mac.update(pixel);
+        self.position += size;
+        let (zn, count) = self.format.decode(pixel);
+        Some(Ok(PixelIntensity::new(zn as f32, count as f32)))
+    }
+
+    /// Whether the intensities read so far are the ones the worker signed, only meaningful
+    /// once [`FragmentStream::next`] returned `None`.
+    pub fn verify_signature(&self) -> bool {
+        self.remaining == 0 && self.mac.verify(&self.signature)
+    }
+
+    // makes sure `needed` unread bytes are buffered, false if the frame does not hold them
+    async fn fill(&mut self, needed: usize) -> NetworkingResult<bool> {
+        let available = self.chunk.len() - self.position;
+        if available >= needed {
+            return Ok(true);
+        }
+        if available + self.remaining < needed {
+            return Ok(false);
+        }
+
+        self.chunk.drain(..self.position);
+        self.position = 0;
+        let length = self
+            .remaining
+            .min(STREAM_CHUNK_SIZE.max(needed - available));
+        let start = self.chunk.len();
+        self.chunk.resize(start + length, 0);
+        read_exact_with_timeout(self.stream, &mut self.chunk[start..], self.timeout).await?;
+        self.remaining -= length;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::networking::{
+        read_message_raw, send_result, DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_READ_TIMEOUT,
+    };
+
+    use super::*;
+
+    // a frame of `rows` rows of 256 pixels, larger than a chunk once past a few rows
+    async fn fragment_frame(
+        format: IntensityFormat,
+        rows: usize,
+        compression: Compression,
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        for pixel in 0..rows * 256 {
+            format.encode(pixel as f64 / 7.0, (pixel % 256) as f64 / 256.0, &mut data);
+        }
+        let mut frame = Vec::new();
+        send_result(&mut frame, r#"{"FragmentResult":{}}"#, &data, compression)
+            .await
+            .unwrap();
+        frame
+    }
+
+    #[tokio::test]
+    async fn streaming_yields_the_intensities_of_the_buffered_read() {
+        for compression in [Compression::None, Compression::Zstd] {
+            for format in [
+                IntensityFormat::U16,
+                IntensityFormat::F32,
+                IntensityFormat::F64,
+            ] {
+                let frame = fragment_frame(format, 64, compression).await;
+
+                let buffered: Vec<PixelIntensity> = {
+                    let message = read_message_raw(&mut frame.as_slice()).await.unwrap();
+                    message.data[SIGNATURE_SIZE..]
+                        .chunks_exact(format.pixel_size())
+                        .map(|pixel| {
+                            let (zn, count) = format.decode(pixel);
+                            PixelIntensity::new(zn as f32, count as f32)
+                        })
+                        .collect()
+                };
+
+                let mut reader = frame.as_slice();
+                let mut fragment = read_fragment_streaming(
+                    &mut reader,
+                    format,
+                    DEFAULT_MAX_MESSAGE_SIZE,
+                    DEFAULT_READ_TIMEOUT,
+                )
+                .await
+                .unwrap();
+                let mut streamed = Vec::new();
+                while let Some(intensity) = fragment.next().await {
+                    streamed.push(intensity.unwrap());
+                }
+
+                assert_eq!(streamed.len(), 64 * 256, "{} {}", compression, format);
+                assert_eq!(streamed, buffered, "{} {}", compression, format);
+                assert!(fragment.verify_signature());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tampered_intensities_fail_the_signature() {
+        let mut frame = fragment_frame(IntensityFormat::F32, 4, Compression::None).await;
+        let last = frame.len() - 1;
+        frame[last] ^= 0x01;
+
+        let mut reader = frame.as_slice();
+        let mut fragment = read_fragment_streaming(
+            &mut reader,
+            IntensityFormat::F32,
+            DEFAULT_MAX_MESSAGE_SIZE,
+            DEFAULT_READ_TIMEOUT,
+        )
+        .await
+        .unwrap();
+        while fragment.next().await.is_some() {}
+
+        assert!(!fragment.verify_signature());
+    }
+}
@@ -1,39 +1,126 @@
+pub mod compression;
 pub mod error;
+pub mod fragment_stream;
 pub mod result;
 pub mod server;
+pub mod session;
+pub mod signature;
+pub mod tls;
+pub mod transport;
 pub mod worker;
 
+use std::{borrow::Cow, time::Duration};
+
 use log::{debug, error};
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::env;
+
+use self::{
+    compression::Compression, error::NetworkingError, result::NetworkingResult,
+    signature::sign_payload,
+};
+
+/// Leads every frame, peers speaking another version are turned away.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Upper bound on the length prefixes accepted from a peer, checked before allocating.
+pub const DEFAULT_MAX_MESSAGE_SIZE: u32 = 64 * 1024 * 1024;
+
+/// How long a single read may stall before the peer is considered gone.
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connections served at once, further workers are turned away.
+pub const DEFAULT_MAX_WORKERS: usize = 256;
+
+/// Pixels a worker is handed per unit of its advertised `maximal_work_load`.
+pub const PIXELS_PER_WORK_UNIT: u64 = 16;
+
+/// How long a shutting down server waits for the open connections to finish.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Frames per second of a Julia animation when none is given.
+pub const DEFAULT_ANIMATION_FPS: u32 = 10;
+
+/// Frames written by a frame export when no count is given.
+pub const DEFAULT_EXPORTED_FRAMES: u32 = 10;
+
+/// How often a busy worker tells the server it is still alive.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a worker may go unheard before the server reports it as stale.
+pub const DEFAULT_WORKER_STALE_AFTER: Duration = Duration::from_secs(15);
 
-use self::result::NetworkingResult;
+/// Iterations per pixel of the tasks handed out until the user changes it.
+pub const DEFAULT_MAX_ITERATION: u32 = 256;
+
+/// Most iterations per pixel a task may ask for, on both ends, unless configured otherwise.
+pub const DEFAULT_MAX_ITERATION_CAP: u32 = 1 << 16;
+
+/// Results a worker keeps to serve repeated tasks, 0 disables the cache.
+pub const DEFAULT_TILE_CACHE_CAPACITY: usize = 64;
+
+/// How long a worker waits before reconnecting after its first error in a row.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Ceiling on the reconnection delay, which doubles with each error in a row.
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Errors in a row after which a worker gives up.
+pub const DEFAULT_MAX_RETRIES: u32 = 10;
 
 #[derive(Debug, Clone)]
 pub struct RawMessage {
     pub message_length: u32,
     pub json_length: u32,
     pub json_message: String,
+    pub compression: Compression,
     pub data: Vec<u8>,
 }
 
-pub async fn send_message(
-    stream: &mut TcpStream,
+/// A frame read into a single buffer, the JSON message and the data are borrowed from it
+/// instead of being copied out. Only compressed data gets a buffer of its own, once inflated.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub message_length: u32,
+    pub json_length: u32,
+    pub compression: Compression,
+    body: Vec<u8>,
+    inflated: Option<Vec<u8>>,
+}
+
+impl Frame {
+    pub fn json_message(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body[..self.json_length as usize])
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.inflated
+            .as_deref()
+            .unwrap_or(&self.body[self.json_length as usize..])
+    }
+}
+
+pub async fn send_message<S: AsyncWrite + Unpin>(
+    stream: &mut S,
     json_message: &[u8],
     data: Option<&[u8]>,
+    compression: Compression,
 ) -> NetworkingResult<()> {
+    let data = match data {
+        Some(data) => compression.compress(data)?,
+        None => Vec::new(),
+    };
     let json_message_size = json_message.len() as u32;
-    let data_size = data.map_or(0, |d| d.len() as u32);
-    let total_message_size = json_message_size + data_size;
+    let total_message_size = json_message_size + data.len() as u32;
+    let json_length = compression.encode_json_length(json_message_size)?;
 
-    let mut buffer = Vec::new();
+    let mut buffer = vec![PROTOCOL_VERSION];
     buffer.extend_from_slice(&total_message_size.to_be_bytes());
-    buffer.extend_from_slice(&json_message_size.to_be_bytes());
+    buffer.extend_from_slice(&json_length.to_be_bytes());
     buffer.extend_from_slice(json_message);
-    if let Some(data) = data {
-        buffer.extend_from_slice(data);
-    }
+    buffer.extend_from_slice(&data);
 
     if let Err(e) = stream.write_all(&buffer).await {
         error!("Failed to send message: {}", e);
@@ -52,40 +139,109 @@ pub async fn send_message(
     Ok(())
 }
 
-pub async fn read_message_length(stream: &mut TcpStream) -> NetworkingResult<u32> {
+async fn read_exact_with_timeout<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    buffer: &mut [u8],
+    timeout: Duration,
+) -> NetworkingResult<()> {
+    match tokio::time::timeout(timeout, stream.read_exact(buffer)).await {
+        Ok(result) => result.map(|_| ()).map_err(Into::into),
+        Err(_) => Err(NetworkingError::Timeout(timeout)),
+    }
+}
+
+pub async fn read_protocol_version<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    timeout: Duration,
+) -> NetworkingResult<()> {
+    let mut version = [0u8; 1];
+    read_exact_with_timeout(stream, &mut version, timeout).await?;
+    if version[0] != PROTOCOL_VERSION {
+        error!(
+            "Peer speaks protocol version {}, expected {}",
+            version[0], PROTOCOL_VERSION
+        );
+        return Err(NetworkingError::UnsupportedVersion {
+            found: version[0],
+            expected: PROTOCOL_VERSION,
+        });
+    }
+    Ok(())
+}
+
+pub async fn read_message_length<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    timeout: Duration,
+) -> NetworkingResult<u32> {
     let mut length_bytes = [0u8; 4];
-    if let Err(e) = stream.read_exact(&mut length_bytes).await {
+    if let Err(e) = read_exact_with_timeout(stream, &mut length_bytes, timeout).await {
         error!("Failed to read message length: {}", e);
-        return Err(e.into());
+        return Err(e);
     }
     let length = u32::from_be_bytes(length_bytes);
     debug!("Read message length: {}", length);
     Ok(length)
 }
 
-pub async fn read_json_message(stream: &mut TcpStream, length: usize) -> NetworkingResult<String> {
+pub fn check_message_size(length: u32, max_message_size: u32) -> NetworkingResult<()> {
+    if length > max_message_size {
+        error!(
+            "Declared length {} exceeds the maximum message size {}",
+            length, max_message_size
+        );
+        return Err(NetworkingError::MessageTooLarge {
+            length,
+            max: max_message_size,
+        });
+    }
+    Ok(())
+}
+
+/// Length of the binary section of a frame, i.e. whatever follows the JSON message.
+pub fn binary_data_length(message_length: u32, json_length: u32) -> NetworkingResult<u32> {
+    message_length.checked_sub(json_length).ok_or_else(|| {
+        error!(
+            "JSON length {} exceeds the total message length {}",
+            json_length, message_length
+        );
+        NetworkingError::MalformedFrame(format!(
+            "JSON length {} exceeds the total message length {}",
+            json_length, message_length
+        ))
+    })
+}
+
+pub async fn read_json_message<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    length: usize,
+    timeout: Duration,
+) -> NetworkingResult<String> {
     let mut json_message = vec![0u8; length];
-    if let Err(e) = stream.read_exact(&mut json_message).await {
+    if let Err(e) = read_exact_with_timeout(stream, &mut json_message, timeout).await {
         error!("Failed to read JSON message: {}", e);
-        return Err(e.into());
+        return Err(e);
     }
     let message = String::from_utf8_lossy(&json_message).to_string();
     debug!("JSON message read successfully, length: {}", length);
     Ok(message)
 }
 
-pub async fn read_binary_data(stream: &mut TcpStream, length: usize) -> NetworkingResult<Vec<u8>> {
+pub async fn read_binary_data<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    length: usize,
+    timeout: Duration,
+) -> NetworkingResult<Vec<u8>> {
     let mut data_message = vec![0u8; length];
-    if let Err(e) = stream.read_exact(&mut data_message).await {
+    if let Err(e) = read_exact_with_timeout(stream, &mut data_message, timeout).await {
         error!("Failed to read binary data: {}", e);
-        return Err(e.into());
+        return Err(e);
     }
     debug!("Binary data read successfully, length: {}", length);
     Ok(data_message)
 }
 
-pub async fn write_json_message(
-    stream: &mut TcpStream,
+pub async fn write_json_message<S: AsyncWrite + Unpin>(
+    stream: &mut S,
     json_message: &str,
 ) -> NetworkingResult<()> {
     let message_bytes = json_message.as_bytes();
@@ -113,7 +269,10 @@ pub async fn write_json_message(
     Ok(())
 }
 
-pub async fn write_binary_data(stream: &mut TcpStream, data: &[u8]) -> NetworkingResult<()> {
+pub async fn write_binary_data<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    data: &[u8],
+) -> NetworkingResult<()> {
     if let Err(e) = stream.write_all(data).await {
         error!("Failed to write binary data: {}", e);
         return Err(e.into());
@@ -129,54 +288,86 @@ pub async fn write_binary_data(stream: &mut TcpStream, data: &[u8]) -> Networkin
     Ok(())
 }
 
-pub async fn send_result(
-    stream: &mut TcpStream,
+pub async fn send_result<S: AsyncWrite + Unpin>(
+    stream: &mut S,
     json_message: &str,
     binary_data: &[u8],
-    signature: &[u8],
+    compression: Compression,
 ) -> NetworkingResult<()> {
     let json_bytes = json_message.as_bytes();
-    let total_message_size = (json_bytes.len() + binary_data.len() + signature.len()) as u32;
+    let signature = sign_payload(env::secret(), json_bytes, binary_data);
+
+    // The signature and the binary data form the data section, compressed as a whole
+    let data = compression.compress(&[signature.as_slice(), binary_data].concat())?;
+    let total_message_size = (json_bytes.len() + data.len()) as u32;
 
-    // Write the total message size
+    // Write the protocol version, then the total message size
+    if let Err(e) = stream.write_u8(PROTOCOL_VERSION).await {
+        error!("Failed to write the protocol version: {}", e);
+        return Err(e.into());
+    }
     if let Err(e) = stream.write_u32(total_message_size).await {
         error!("Failed to write total message size: {}", e);
         return Err(e.into());
     }
 
-    // Write components: JSON message, signature, and binary data
-    write_json_message(stream, json_message).await?;
-    write_binary_data(stream, signature).await?;
-    write_binary_data(stream, binary_data).await?;
+    // Write components: JSON length tagged with the codec, JSON message, and data section
+    let json_length = compression.encode_json_length(json_bytes.len() as u32)?;
+    if let Err(e) = stream.write_u32(json_length).await {
+        error!("Failed to write JSON message length: {}", e);
+        return Err(e.into());
+    }
+    write_binary_data(stream, json_bytes).await?;
+    write_binary_data(stream, &data).await?;
 
     debug!(
-        "Result sent: {} bytes of JSON, {} bytes of signature, and {} bytes of binary data",
+        "Result sent: {} bytes of JSON, {} bytes of signature, and {} bytes of binary data ({} bytes on the wire, {})",
         json_bytes.len(),
         signature.len(),
-        binary_data.len()
+        binary_data.len(),
+        data.len(),
+        compression
     );
     Ok(())
 }
 
-pub async fn read_message_raw(stream: &mut TcpStream) -> NetworkingResult<RawMessage> {
+pub async fn read_message_raw<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> NetworkingResult<RawMessage> {
+    read_message_raw_with_limit(stream, DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_READ_TIMEOUT).await
+}
+
+pub async fn read_message_raw_with_limit<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    max_message_size: u32,
+    timeout: Duration,
+) -> NetworkingResult<RawMessage> {
     debug!("Starting to read a raw message from the stream.");
+    read_protocol_version(stream, timeout).await?;
 
     // Read the overall message length.
-    let message_length = read_message_length(stream).await.map_err(|e| {
+    let message_length = read_message_length(stream, timeout).await.map_err(|e| {
         error!("Failed to read message length: {}", e);
         e
     })?;
     debug!("Message length: {}", message_length);
+    check_message_size(message_length, max_message_size)?;
 
-    // Read the length of the JSON message.
-    let json_length = read_message_length(stream).await.map_err(|e| {
+    // Read the length of the JSON message, its most significant byte holds the data codec.
+    let json_length_prefix = read_message_length(stream, timeout).await.map_err(|e| {
         error!("Failed to read JSON length: {}", e);
         e
     })?;
-    debug!("JSON message length: {}", json_length);
+    let (compression, json_length) = Compression::decode_json_length(json_length_prefix)?;
+    debug!(
+        "JSON message length: {}, data compression: {}",
+        json_length, compression
+    );
+    check_message_size(json_length, max_message_size)?;
+    let data_length = binary_data_length(message_length, json_length)?;
 
     // Read the JSON message itself.
-    let json_message = read_json_message(stream, json_length as usize)
+    let json_message = read_json_message(stream, json_length as usize, timeout)
         .await
         .map_err(|e| {
             error!("Failed to read JSON message: {}", e);
@@ -184,10 +375,9 @@ pub async fn read_message_raw(stream: &mut TcpStream) -> NetworkingResult<RawMes
         })?;
     debug!("Successfully read JSON message.");
 
-    // Calculate the length of the binary data and read it.
-    let binary_data_length = message_length - json_length;
-    let data = if binary_data_length > 0 {
-        read_binary_data(stream, binary_data_length as usize)
+    // Read the binary data that follows the JSON message.
+    let data = if data_length > 0 {
+        read_binary_data(stream, data_length as usize, timeout)
             .await
             .map_err(|e| {
                 error!("Failed to read binary data: {}", e);
@@ -196,12 +386,182 @@ pub async fn read_message_raw(stream: &mut TcpStream) -> NetworkingResult<RawMes
     } else {
         Vec::new()
     };
+    let data = compression.decompress(&data, max_message_size)?;
     debug!("Successfully read binary data of length: {}", data.len());
 
     Ok(RawMessage {
         message_length,
         json_length,
         json_message,
+        compression,
         data,
     })
 }
+
+/// Reads a frame like [`read_message_raw_with_limit`], without copying its JSON message and
+/// data out of the read buffer, which matters for the large fragments.
+pub async fn read_frame_with_limit<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    max_message_size: u32,
+    timeout: Duration,
+) -> NetworkingResult<Frame> {
+    read_protocol_version(stream, timeout).await?;
+    let message_length = read_message_length(stream, timeout).await?;
+    check_message_size(message_length, max_message_size)?;
+
+    let json_length_prefix = read_message_length(stream, timeout).await?;
+    let (compression, json_length) = Compression::decode_json_length(json_length_prefix)?;
+    check_message_size(json_length, max_message_size)?;
+    binary_data_length(message_length, json_length)?;
+
+    let mut body = vec![0u8; message_length as usize];
+    if let Err(e) = read_exact_with_timeout(stream, &mut body, timeout).await {
+        error!("Failed to read the frame body: {}", e);
+        return Err(e);
+    }
+    let inflated = match compression {
+        Compression::None => None,
+        _ => Some(compression.decompress(&body[json_length as usize..], max_message_size)?),
+    };
+    debug!(
+        "Read a frame of {} bytes, {} of JSON, data compression: {}",
+        message_length, json_length, compression
+    );
+
+    Ok(Frame {
+        message_length,
+        json_length,
+        compression,
+        body,
+        inflated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::models::pixel::pixel_intensity::IntensityFormat;
+
+    use super::signature::SIGNATURE_SIZE;
+
+    use super::*;
+
+    async fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn oversized_length_prefix_is_rejected_before_reading() {
+        let (mut client, mut server) = connected_pair().await;
+        client.write_u8(PROTOCOL_VERSION).await.unwrap();
+        client.write_u32(u32::MAX).await.unwrap();
+        client.write_u32(16).await.unwrap();
+
+        let result = read_message_raw_with_limit(&mut server, 1024, DEFAULT_READ_TIMEOUT).await;
+
+        assert!(matches!(
+            result,
+            Err(NetworkingError::MessageTooLarge { length, max: 1024 }) if length == u32::MAX
+        ));
+    }
+
+    #[tokio::test]
+    async fn json_length_larger_than_message_is_a_malformed_frame() {
+        let (mut client, mut server) = connected_pair().await;
+        client.write_u8(PROTOCOL_VERSION).await.unwrap();
+        client.write_u32(4).await.unwrap();
+        client.write_u32(10).await.unwrap();
+
+        let result = read_message_raw(&mut server).await;
+
+        assert!(matches!(result, Err(NetworkingError::MalformedFrame(_))));
+    }
+
+    #[tokio::test]
+    async fn stalled_frame_times_out() {
+        let (mut client, mut server) = connected_pair().await;
+        client.write_u8(PROTOCOL_VERSION).await.unwrap();
+        client.write_u32(64).await.unwrap();
+
+        let timeout = Duration::from_millis(50);
+        let result = read_message_raw_with_limit(&mut server, 1024, timeout).await;
+
+        assert!(matches!(result, Err(NetworkingError::Timeout(t)) if t == timeout));
+    }
+
+    #[tokio::test]
+    async fn frames_from_another_protocol_version_are_rejected() {
+        let (mut client, mut server) = connected_pair().await;
+        client.write_u8(PROTOCOL_VERSION + 1).await.unwrap();
+        client.write_u32(0).await.unwrap();
+
+        let result = read_message_raw(&mut server).await;
+
+        assert!(matches!(
+            result,
+            Err(NetworkingError::UnsupportedVersion { found, expected: PROTOCOL_VERSION })
+                if found == PROTOCOL_VERSION + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn frames_from_the_same_protocol_version_are_accepted() {
+        let (mut client, mut server) = connected_pair().await;
+        send_message(&mut client, b"{}", None, Compression::None)
+            .await
+            .unwrap();
+
+        let message = read_message_raw(&mut server).await.unwrap();
+
+        assert_eq!(message.json_message, "{}");
+    }
+
+    #[tokio::test]
+    async fn frames_parse_the_same_as_raw_messages() {
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            for format in [
+                IntensityFormat::U16,
+                IntensityFormat::F32,
+                IntensityFormat::F64,
+            ] {
+                let mut data = Vec::new();
+                for pixel in 0..1024 {
+                    format.encode(pixel as f64 / 1024.0, pixel as f64 / 2048.0, &mut data);
+                }
+                let mut frame_bytes = Vec::new();
+                send_result(
+                    &mut frame_bytes,
+                    r#"{"FragmentResult":{}}"#,
+                    &data,
+                    compression,
+                )
+                .await
+                .unwrap();
+
+                let raw = read_message_raw(&mut frame_bytes.as_slice()).await.unwrap();
+                let frame = read_frame_with_limit(
+                    &mut frame_bytes.as_slice(),
+                    DEFAULT_MAX_MESSAGE_SIZE,
+                    DEFAULT_READ_TIMEOUT,
+                )
+                .await
+                .unwrap();
+
+                assert_eq!(frame.json_message(), raw.json_message);
+                assert_eq!(frame.data(), raw.data.as_slice());
+                assert_eq!(
+                    format.counts(&frame.data()[SIGNATURE_SIZE..]),
+                    format.counts(&raw.data[SIGNATURE_SIZE..]),
+                    "{} {}",
+                    compression,
+                    format
+                );
+            }
+        }
+    }
+}
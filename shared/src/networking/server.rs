@@ -1,28 +1,51 @@
-use std::{collections::HashMap, net::SocketAddr};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use complex_rs::complex::Complex;
+use log::warn;
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    dtos::rendering_data::RenderingData,
+    dtos::{
+        iteration_histogram::IterationHistogram, rendering_data::RenderingData,
+        server_dto::ServerDto, tile_timing::TileTiming, worker_metrics::WorkerMetrics,
+        worker_status::WorkerStatus,
+    },
     models::{
         fractal::{
-            fractal_descriptor::FractalDescriptor, iterated_sin_z::IteratedSinZ, julia::Julia,
-            mandelbrot::Mandelbrot, newton_raphson_3::NewtonRaphsonZ3,
-            newton_raphson_4::NewtonRaphsonZ4, nova_newton_raphson_z3::NovaNewtonRaphsonZ3,
+            fractal_descriptor::{FractalDescriptor, UnknownFractalError},
+            iterated_sin_z::IteratedSinZ,
+            julia::Julia,
+            mandelbrot::Mandelbrot,
+            newton_raphson::NewtonRaphson,
+            nova_newton_raphson_z3::NovaNewtonRaphsonZ3,
             nova_newton_raphson_z4::NovaNewtonRaphsonZ4,
         },
-        fragments::fragment_task::FragmentTask,
+        fragments::{fragment_result::FragmentResult, fragment_task::FragmentTask},
+        pixel::pixel_intensity::IntensityFormat,
         point::Point,
         range::Range,
-        resolution::Resolution,
+        tile::Tile,
         u8_data::U8Data,
     },
+    rendering::color::PaletteHandler,
 };
 
-use super::worker::Worker;
+use super::{
+    error::NetworkingError, result::NetworkingResult, session::SessionRecorder,
+    transport::Transport, worker::Worker, DEFAULT_ANIMATION_FPS, DEFAULT_DRAIN_TIMEOUT,
+    DEFAULT_EXPORTED_FRAMES, DEFAULT_MAX_ITERATION, DEFAULT_MAX_ITERATION_CAP,
+    DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_MAX_WORKERS, DEFAULT_READ_TIMEOUT,
+    DEFAULT_WORKER_STALE_AFTER, PIXELS_PER_WORK_UNIT,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -33,6 +56,40 @@ pub struct ServerConfig {
     pub tiles: u32,
     pub range: Range,
     pub speed: f64,
+    pub task_timeout: Duration,
+    pub max_message_size: u32,
+    pub read_timeout: Duration,
+    pub fractal: Option<String>,
+    pub palette: Option<String>,
+    /// Renders a single frame to this PNG file instead of opening a window.
+    pub render_to: Option<PathBuf>,
+    /// Renders `frames` frames into numbered PNGs in this directory instead of opening a window.
+    pub export_frames: Option<PathBuf>,
+    pub frames: u32,
+    /// View state restored on startup, if the file exists, and saved on shutdown.
+    pub state_file: Option<PathBuf>,
+    pub worker_stale_after: Duration,
+    pub max_workers: usize,
+    /// Fragments waiting to be drawn before the connections hold off, one per tile by default.
+    pub render_queue_capacity: Option<usize>,
+    pub drain_timeout: Duration,
+    /// Values swept by the Julia constant, one per frame, when animating.
+    pub julia_path: Option<Vec<Complex>>,
+    pub animation_fps: u32,
+    pub intensity_format: IntensityFormat,
+    /// Ceiling on the iterations of the tasks handed out.
+    pub max_iteration_cap: u32,
+    /// Most tiles a worker may hold at once, so a greedy one leaves some for the others.
+    pub worker_tile_quota: Option<usize>,
+    /// PEM certificate chain and private key, workers connect over TLS when both are set.
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// Listens on this UNIX domain socket instead of `address:port`.
+    pub unix_socket: Option<PathBuf>,
+    /// Records every fragment result received to this file, see `replay`.
+    pub record_session: Option<PathBuf>,
+    /// Draws the results recorded in this file instead of listening for workers.
+    pub replay: Option<PathBuf>,
 }
 
 impl ServerConfig {
@@ -41,6 +98,9 @@ impl ServerConfig {
         let max = Point::new(1.2, 1.2);
         let range = Range::new(min, max);
         let speed = 1.0;
+        let task_timeout = Duration::from_secs(30);
+        let max_message_size = DEFAULT_MAX_MESSAGE_SIZE;
+        let read_timeout = DEFAULT_READ_TIMEOUT;
 
         Self {
             address,
@@ -50,26 +110,103 @@ impl ServerConfig {
             tiles,
             range,
             speed,
+            task_timeout,
+            max_message_size,
+            read_timeout,
+            fractal: None,
+            palette: None,
+            render_to: None,
+            export_frames: None,
+            frames: DEFAULT_EXPORTED_FRAMES,
+            state_file: None,
+            worker_stale_after: DEFAULT_WORKER_STALE_AFTER,
+            max_workers: DEFAULT_MAX_WORKERS,
+            render_queue_capacity: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            julia_path: None,
+            animation_fps: DEFAULT_ANIMATION_FPS,
+            intensity_format: IntensityFormat::default(),
+            max_iteration_cap: DEFAULT_MAX_ITERATION_CAP,
+            worker_tile_quota: None,
+            tls_cert: None,
+            tls_key: None,
+            unix_socket: None,
+            record_session: None,
+            replay: None,
         }
     }
+
+    pub fn transport(&self) -> Transport {
+        match &self.unix_socket {
+            Some(path) => Transport::Unix { path: path.clone() },
+            None => Transport::Tcp {
+                addr: format!("{}:{}", self.address, self.port),
+            },
+        }
+    }
+
+    pub fn render_queue_capacity(&self) -> usize {
+        self.render_queue_capacity
+            .unwrap_or((self.tiles * self.tiles) as usize)
+            .max(1)
+    }
+}
+
+/// What the user navigated to, persisted across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewState {
+    pub range: Range,
+    pub current_fractal: usize,
+    pub palette: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A sweep of the Julia constant along `path`, one point per frame.
+#[derive(Debug, Clone)]
+pub struct JuliaAnimation {
+    pub path: Vec<Complex>,
+    pub frame: usize,
+    pub frame_interval: Duration,
 }
 
 #[derive(Clone, Debug)]
 pub struct Server {
     pub config: ServerConfig,
     pub render_tx: Sender<RenderingData>,
-    pub tiles: Vec<Range>,
+    pub tiles: Vec<Tile>,
+    pub dispatched_tiles: Vec<Tile>,
     pub range: Range,
     pub current_fractal: usize,
     pub fractals: Vec<FractalDescriptor>,
     pub workers: HashMap<SocketAddr, Worker>,
+    pub last_seen: HashMap<SocketAddr, Instant>,
+    /// Throughput of each worker, keyed by worker name.
+    pub metrics: HashMap<String, WorkerMetrics>,
+    pub palette: PaletteHandler,
+    pub tasks: VecDeque<FragmentTask>,
+    pub inflight_tasks: HashMap<SocketAddr, (FragmentTask, Instant)>,
+    /// Hands out the tasks closest to the center of the view first, so it fills in outwards.
+    pub priority_center: bool,
+    pub animation: Option<JuliaAnimation>,
+    /// Fragments dropped because the window fell behind, their tasks are computed again.
+    pub dropped_fragments: u64,
+    /// Distribution of the iteration counts received, to tune `max_iteration` from.
+    pub histogram: IterationHistogram,
+    pub tile_timings: Vec<TileTiming>,
+    /// Iterations per pixel of the tasks created from now on.
+    pub max_iteration: u32,
+    /// No task is handed out while paused, the workers retry until dispatch resumes.
+    pub paused: bool,
+    /// Keeps the fragment results received when recording a session.
+    pub recorder: Option<Arc<SessionRecorder>>,
 }
 
 impl Server {
     pub fn new(config: ServerConfig, render_tx: Sender<RenderingData>) -> Self {
-        let range = config.range;
+        let range = fit_aspect_ratio(config.range, config.width, config.height);
         let workers: HashMap<SocketAddr, Worker> = HashMap::new();
-        let tiles = Server::generate_tiles(&range, config.tiles);
+        let tiles = Server::generate_tiles(&range, &config);
         let fractals: Vec<FractalDescriptor> = vec![
             FractalDescriptor::Mandelbrot(Mandelbrot::new()),
             FractalDescriptor::Julia(Julia::new(
@@ -88,61 +225,479 @@ impl Server {
             )),
             FractalDescriptor::IteratedSinZ(IteratedSinZ::new(Complex { re: 1.0, im: 0.3 })),
             FractalDescriptor::IteratedSinZ(IteratedSinZ::new(Complex { re: 0.2, im: 1.0 })),
-            FractalDescriptor::NewtonRaphsonZ3(NewtonRaphsonZ3::new()),
-            FractalDescriptor::NewtonRaphsonZ4(NewtonRaphsonZ4::new()),
+            FractalDescriptor::NewtonRaphson(NewtonRaphson::new(3)),
+            FractalDescriptor::NewtonRaphson(NewtonRaphson::new(4)),
             FractalDescriptor::NovaNewtonRapshonZ3(NovaNewtonRaphsonZ3::new()),
             FractalDescriptor::NovaNewtonRapshonZ4(NovaNewtonRaphsonZ4::new()),
         ];
 
+        let current_fractal = match config.fractal.as_deref() {
+            Some(name) => fractals
+                .iter()
+                .position(|fractal| fractal.name().eq_ignore_ascii_case(name))
+                .unwrap_or_else(|| {
+                    warn!("Unknown fractal {}, starting with the first one", name);
+                    0
+                }),
+            None => 0,
+        };
+
+        let mut palette = PaletteHandler::new();
+        if let Some(name) = config.palette.as_deref() {
+            if let Err(e) = palette.set_palette_by_name(name) {
+                warn!("{}, starting with the default one", e);
+            }
+        }
+
         Self {
             config,
             render_tx,
             tiles,
+            dispatched_tiles: Vec::new(),
             range,
-            current_fractal: 0,
+            current_fractal,
             fractals,
             workers,
+            last_seen: HashMap::new(),
+            metrics: HashMap::new(),
+            palette,
+            tasks: VecDeque::new(),
+            inflight_tasks: HashMap::new(),
+            priority_center: false,
+            animation: None,
+            dropped_fragments: 0,
+            histogram: IterationHistogram::default(),
+            tile_timings: Vec::new(),
+            max_iteration: DEFAULT_MAX_ITERATION,
+            paused: false,
+            recorder: None,
         }
     }
 
     pub fn cycle_fractal(&mut self) {
-        self.current_fractal = (self.current_fractal + 1) % self.fractals.len();
-        self.regenerate_tiles();
+        self.select_fractal((self.current_fractal + 1) % self.fractals.len());
+    }
+
+    pub fn cycle_fractal_prev(&mut self) {
+        let count = self.fractals.len();
+        self.select_fractal((self.current_fractal + count - 1) % count);
+    }
+
+    fn select_fractal(&mut self, index: usize) {
+        self.current_fractal = index;
+        self.range = self.fractals[index].default_range();
+        self.invalidate_and_retile();
+    }
+
+    /// Changes the iterations per pixel, within `1..=max_iteration_cap`, and recomputes the
+    /// view with them.
+    pub fn set_max_iteration(&mut self, max_iteration: u32) {
+        self.max_iteration = max_iteration.clamp(1, self.config.max_iteration_cap.max(1));
+        self.invalidate_and_retile();
+    }
+
+    /// Switches to the first fractal of the given kind, e.g. `mandelbrot`, and frames it.
+    pub fn set_fractal_by_name(&mut self, name: &str) -> Result<(), UnknownFractalError> {
+        let index = self
+            .fractals
+            .iter()
+            .position(|fractal| fractal.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownFractalError(name.to_string()))?;
+
+        self.select_fractal(index);
+        Ok(())
+    }
+
+    /// Starts sweeping the Julia constant along `path`, switching to the Julia set if needed.
+    /// The first point is shown right away, `step_animation` moves to the next ones.
+    pub fn animate_julia(&mut self, path: Vec<Complex>, fps: u32) {
+        if path.is_empty() {
+            warn!("Ignoring a Julia animation without any point");
+            return;
+        }
+        if !matches!(
+            self.fractals[self.current_fractal],
+            FractalDescriptor::Julia(_)
+        ) {
+            // the Julia set is part of the fractals the server starts with
+            let _ = self.set_fractal_by_name("julia");
+        }
+
+        self.animation = Some(JuliaAnimation {
+            path,
+            frame: 0,
+            frame_interval: Duration::from_secs_f64(1.0 / fps.max(1) as f64),
+        });
+        self.show_animation_frame();
+    }
+
+    /// Moves the animation to the next point of its path, looping at the end, and returns
+    /// whether an animation is running.
+    pub fn step_animation(&mut self) -> bool {
+        let Some(animation) = self.animation.as_mut() else {
+            return false;
+        };
+        animation.frame = (animation.frame + 1) % animation.path.len();
+        self.show_animation_frame();
+        self.animation.is_some()
     }
 
-    pub fn register_worker(&mut self, addr: SocketAddr, worker: Worker) {
+    /// Whether every task of the current view came back.
+    pub fn frame_complete(&self) -> bool {
+        self.tiles.is_empty() && self.tasks.is_empty() && self.inflight_tasks.is_empty()
+    }
+
+    fn show_animation_frame(&mut self) {
+        let Some(animation) = &self.animation else {
+            return;
+        };
+        let c = animation.path[animation.frame];
+
+        match &mut self.fractals[self.current_fractal] {
+            FractalDescriptor::Julia(julia) => julia.c = c,
+            // the user moved on to another fractal
+            _ => {
+                self.animation = None;
+                return;
+            }
+        }
+        self.invalidate_and_retile();
+    }
+
+    pub fn to_dto(&self) -> ServerDto {
+        ServerDto {
+            config: self.config.clone(),
+            range: self.range,
+            fractal: self.fractals[self.current_fractal].clone(),
+            palette: self.palette.current_palette().name().to_string(),
+            remaining_tiles: self.tiles.len(),
+            queued_tasks: self.tasks.len(),
+            inflight_tasks: self.inflight_tasks.len(),
+            workers: self
+                .workers
+                .iter()
+                .map(|(addr, worker)| (addr.to_string(), worker.clone()))
+                .collect(),
+            worker_statuses: self.worker_statuses(self.config.worker_stale_after),
+            metrics: self.metrics.clone(),
+            dropped_fragments: self.dropped_fragments,
+            tile_timings: self.tile_timings.clone(),
+        }
+    }
+
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            range: self.range,
+            current_fractal: self.current_fractal,
+            palette: self.palette.current_palette().name().to_string(),
+            width: self.config.width,
+            height: self.config.height,
+        }
+    }
+
+    pub fn save_state(&self, path: &Path) -> NetworkingResult<()> {
+        let state = serde_json::to_string_pretty(&self.view_state())?;
+        fs::write(path, state)?;
+        Ok(())
+    }
+
+    pub fn load_state(&mut self, path: &Path) -> NetworkingResult<()> {
+        let state: ViewState = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        if state.current_fractal >= self.fractals.len() {
+            return Err(NetworkingError::Error(
+                format!("no fractal at index {}", state.current_fractal).into(),
+            ));
+        }
+        if let Err(e) = self.palette.set_palette_by_name(&state.palette) {
+            warn!("{}, keeping the current one", e);
+        }
+        self.range = state.range;
+        self.current_fractal = state.current_fractal;
+        self.config.width = state.width;
+        self.config.height = state.height;
+        self.invalidate_and_retile();
+
+        Ok(())
+    }
+
+    /// Registers `worker` under its requested name, suffixed (`name-2`, `name-3`...) when a worker
+    /// at another address already holds it, and returns the name it was given.
+    pub fn register_worker(&mut self, addr: SocketAddr, mut worker: Worker) -> String {
+        let taken = |name: &str| {
+            self.workers
+                .iter()
+                .any(|(other, known)| *other != addr && known.name == name)
+        };
+
+        let mut name = worker.name.clone();
+        let mut suffix = 2;
+        while taken(&name) {
+            name = format!("{}-{}", worker.name, suffix);
+            suffix += 1;
+        }
+
+        worker.name = name.clone();
         self.workers.insert(addr, worker);
+        self.record_activity(&addr);
+        name
+    }
+
+    pub fn unregister_worker(&mut self, addr: &SocketAddr) -> Option<Worker> {
+        self.last_seen.remove(addr);
+        self.workers.remove(addr)
+    }
+
+    /// Notes that a message, e.g. a heartbeat, just came in from `addr`.
+    pub fn record_activity(&mut self, addr: &SocketAddr) {
+        self.last_seen.insert(*addr, Instant::now());
+    }
+
+    pub fn record_completed_fragment(&mut self, worker_name: &str, pixels: u64) {
+        let metrics = self.metrics.entry(worker_name.to_string()).or_default();
+        metrics.completed_fragments += 1;
+        metrics.total_pixels += pixels;
+    }
+
+    /// Keeps the time taken by the fragment covering `range` for the heatmap of the view.
+    pub fn record_tile_timing(&mut self, range: Range, compute_ms: u32) {
+        self.tile_timings.push(TileTiming { range, compute_ms });
+    }
+
+    /// Reports every known worker, flagging those unheard of for longer than `stale_after`.
+    pub fn worker_statuses(&self, stale_after: Duration) -> Vec<WorkerStatus> {
+        self.workers
+            .iter()
+            .map(|(addr, worker)| {
+                let since_last_seen = self
+                    .last_seen
+                    .get(addr)
+                    .map(Instant::elapsed)
+                    .unwrap_or(Duration::MAX);
+                WorkerStatus {
+                    address: addr.to_string(),
+                    name: worker.name.clone(),
+                    maximal_work_load: worker.maximal_work_load,
+                    seconds_since_last_seen: since_last_seen.as_secs_f64(),
+                    stale: since_last_seen > stale_after,
+                }
+            })
+            .collect()
     }
 
     pub fn get_worker(&self, addr: &SocketAddr) -> Option<&Worker> {
         self.workers.get(addr)
     }
 
+    pub fn enqueue_task(&mut self, task: FragmentTask) {
+        self.tasks.push_back(task);
+    }
+
+    pub fn dequeue_task(&mut self) -> Option<FragmentTask> {
+        if self.paused {
+            return None;
+        }
+        if !self.priority_center {
+            return self.tasks.pop_front();
+        }
+
+        let closest = self.closest_to_center(self.tasks.iter().map(|task| &task.range))?;
+        self.tasks.remove(closest)
+    }
+
+    pub fn set_priority_center(&mut self, enabled: bool) {
+        self.priority_center = enabled;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // index of the range whose middle is nearest to the middle of the view
+    fn closest_to_center<'a>(&self, ranges: impl Iterator<Item = &'a Range>) -> Option<usize> {
+        let center = self.range.center();
+        let distance = |range: &Range| range.center().distance(&center);
+
+        ranges
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, _)| index)
+    }
+
+    pub fn mark_task_inflight(&mut self, addr: SocketAddr, task: FragmentTask) {
+        if let Some((orphan, _)) = self.inflight_tasks.insert(addr, (task, Instant::now())) {
+            self.enqueue_task(orphan);
+        }
+    }
+
+    // a result may be delivered after its worker reconnected and its task got reclaimed, so the
+    // task is matched on its uuid, or on its range for workers that do not echo it, rather than
+    // on the address it was handed out to
+    pub fn complete_task(&mut self, result: &FragmentResult) -> Option<FragmentTask> {
+        let answers = |task: &FragmentTask| match result.task_uuid {
+            Some(task_uuid) => task.task_uuid == task_uuid,
+            None => task.range == result.range,
+        };
+
+        let inflight = self
+            .inflight_tasks
+            .iter()
+            .find(|(_, (task, _))| answers(task))
+            .map(|(addr, _)| *addr);
+        if let Some(addr) = inflight {
+            return self.inflight_tasks.remove(&addr).map(|(task, _)| task);
+        }
+
+        let queued = self.tasks.iter().position(answers)?;
+        self.tasks.remove(queued)
+    }
+
+    pub fn reclaim_task(&mut self, addr: &SocketAddr) -> bool {
+        match self.inflight_tasks.remove(addr) {
+            Some((task, _)) => {
+                self.enqueue_task(task);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-enqueues every in-flight task older than `timeout`, returning how many were reclaimed.
+    pub fn reclaim_stale_tasks(&mut self, timeout: Duration) -> usize {
+        let stale: Vec<SocketAddr> = self
+            .inflight_tasks
+            .iter()
+            .filter(|(_, (_, dispatched_at))| dispatched_at.elapsed() >= timeout)
+            .map(|(addr, _)| *addr)
+            .collect();
+
+        stale.iter().filter(|addr| self.reclaim_task(addr)).count()
+    }
+
+    // queued tasks (e.g. handed back by a worker) go out before new tiles are cut
+    pub fn next_task(&mut self) -> Option<FragmentTask> {
+        self.dequeue_task().or_else(|| self.create_fragment_task())
+    }
+
+    /// Hands out a task sized for a worker advertising `work_load`: the next task is widened
+    /// with the queued or uncut tiles beside it until it holds `work_load * PIXELS_PER_WORK_UNIT`
+    /// pixels, its row runs out or it would exceed the worker tile quota.
+    pub fn create_fragment_task_for(&mut self, work_load: u32) -> Option<FragmentTask> {
+        let mut task = self.next_task()?;
+        let budget = work_load as u64 * PIXELS_PER_WORK_UNIT;
+        let quota = self.config.worker_tile_quota.unwrap_or(usize::MAX);
+        let mut tiles = self.tiles_in(&task.range);
+
+        while (task.resolution.nx as u64 * task.resolution.ny as u64) < budget {
+            let Some(neighbour) = self.take_neighbour(&task.range) else {
+                break;
+            };
+            let neighbour_tiles = self.tiles_in(&neighbour.range);
+            if tiles + neighbour_tiles > quota {
+                self.enqueue_task(neighbour);
+                break;
+            }
+            tiles += neighbour_tiles;
+            let (left, right) = if neighbour.range.min.x < task.range.min.x {
+                (neighbour.range, task.range)
+            } else {
+                (task.range, neighbour.range)
+            };
+            task.range = Range::new(left.min, right.max);
+            task.resolution.nx += neighbour.resolution.nx;
+        }
+
+        Some(task)
+    }
+
+    /// Tiles of the current view held by the worker at `addr`.
+    pub fn inflight_tiles(&self, addr: &SocketAddr) -> usize {
+        self.inflight_tasks
+            .get(addr)
+            .map_or(0, |(task, _)| self.tiles_in(&task.range))
+    }
+
+    // tasks are cut along the tiles of the view, so their ranges share the tiles' bounds
+    fn tiles_in(&self, range: &Range) -> usize {
+        self.dispatched_tiles
+            .iter()
+            .filter(|tile| {
+                tile.range.min.x >= range.min.x
+                    && tile.range.max.x <= range.max.x
+                    && tile.range.min.y >= range.min.y
+                    && tile.range.max.y <= range.max.y
+            })
+            .count()
+    }
+
+    // the task or tile sharing a whole vertical edge with `range`, either side
+    fn take_neighbour(&mut self, range: &Range) -> Option<FragmentTask> {
+        let same_row = |other: &Range| other.min.y == range.min.y && other.max.y == range.max.y;
+        let beside = |other: &Range| other.min.x == range.max.x || other.max.x == range.min.x;
+
+        if let Some(queued) = self
+            .tasks
+            .iter()
+            .position(|task| same_row(&task.range) && beside(&task.range))
+        {
+            return self.tasks.remove(queued);
+        }
+
+        let uncut = self
+            .tiles
+            .iter()
+            .position(|tile| same_row(&tile.range) && beside(&tile.range))?;
+        let tile = self.tiles.remove(uncut);
+        self.dispatched_tiles.push(tile);
+        Some(self.task_for_tile(tile))
+    }
+
     pub fn create_fragment_task(&mut self) -> Option<FragmentTask> {
-        let config = self.config.clone();
-
-        if let Some(range) = self.get_random_tile() {
-            let id = U8Data::new(0, 16);
-            let fractal_descriptor = self.fractals[self.current_fractal].clone();
-            // TODO: the max iterations should change based on the current fractal
-            let max_iterations = 256;
-            let resolution = self.calculate_resolution(config.width, config.height, config.tiles);
-            let range = range;
-
-            Some(FragmentTask::new(
-                id,
-                fractal_descriptor,
-                max_iterations,
-                resolution,
-                range,
-            ))
-        } else {
-            None
+        if self.paused {
+            return None;
+        }
+        let tile = self.get_random_tile()?;
+        self.dispatched_tiles.push(tile);
+
+        Some(self.task_for_tile(tile))
+    }
+
+    fn task_for_tile(&self, tile: Tile) -> FragmentTask {
+        let id = U8Data::new(0, 16);
+        let fractal_descriptor = self.fractals[self.current_fractal].clone();
+        // TODO: the max iterations should change based on the current fractal
+        let max_iterations = self.max_iteration;
+
+        let mut task = FragmentTask::new(
+            id,
+            fractal_descriptor,
+            max_iterations,
+            tile.resolution,
+            tile.range,
+        );
+        task.intensity_format = self.config.intensity_format;
+        task.clamp_max_iteration(self.config.max_iteration_cap);
+        task
+    }
+
+    /// Forgets the tasks of the previous view and queues one for every tile of the current one.
+    pub fn invalidate_and_retile(&mut self) {
+        self.enforce_aspect_ratio();
+        self.tasks.clear();
+        self.inflight_tasks.clear();
+        self.dispatched_tiles.clear();
+        self.tile_timings.clear();
+        self.tiles = Server::generate_tiles(&self.range, &self.config);
+
+        while let Some(task) = self.create_fragment_task() {
+            self.enqueue_task(task);
         }
     }
 
-    pub fn regenerate_tiles(&mut self) {
-        self.tiles = Server::generate_tiles(&self.range, self.config.tiles);
+    /// Widens the range along one axis so it has the proportions of the canvas, keeping its
+    /// center, so that the fractal is not stretched.
+    pub fn enforce_aspect_ratio(&mut self) {
+        self.range = fit_aspect_ratio(self.range, self.config.width, self.config.height);
     }
 
     pub fn move_right(&mut self) {
@@ -165,15 +720,23 @@ impl Server {
         let tile_width = (self.range.max.x - self.range.min.x) / self.config.tiles as f64;
         let tile_height = (self.range.max.y - self.range.min.y) / self.config.tiles as f64;
 
-        let dx = tile_width * x;
-        let dy = tile_height * y;
+        self.translate(tile_width * x, tile_height * y);
+    }
 
-        self.range.min.x += dx;
-        self.range.max.x += dx;
-        self.range.min.y += dy;
-        self.range.max.y += dy;
+    /// Moves the view by a distance given in canvas pixels, e.g. a mouse drag.
+    pub fn pan(&mut self, dx_pixels: f64, dy_pixels: f64) {
+        let dx = dx_pixels / self.config.width as f64 * (self.range.max.x - self.range.min.x);
+        let dy = dy_pixels / self.config.height as f64 * (self.range.max.y - self.range.min.y);
 
-        self.regenerate_tiles();
+        self.translate(dx, dy);
+    }
+
+    fn translate(&mut self, dx: f64, dy: f64) {
+        let offset = Point::new(dx, dy);
+        self.range.min = self.range.min + offset;
+        self.range.max = self.range.max + offset;
+
+        self.invalidate_and_retile();
     }
 
     pub fn zoom(&mut self, factor: f64) {
@@ -188,12 +751,35 @@ impl Server {
         self.range.min.y += dy;
         self.range.max.y -= dy;
 
-        self.regenerate_tiles();
+        self.invalidate_and_retile();
     }
 
-    pub fn get_random_tile(&mut self) -> Option<Range> {
+    /// Scales the view by `factor` while keeping the point under the cursor, given in canvas
+    /// pixels, in place. A factor below 1 zooms in.
+    pub fn zoom_at(&mut self, factor: f64, cursor_x: f64, cursor_y: f64) {
+        let fx = cursor_x / self.config.width as f64;
+        let fy = cursor_y / self.config.height as f64;
+        let width = self.range.max.x - self.range.min.x;
+        let height = self.range.max.y - self.range.min.y;
+
+        let anchor = Point::new(
+            self.range.min.x + fx * width,
+            self.range.min.y + fy * height,
+        );
+        let (width, height) = (width * factor, height * factor);
+
+        self.range.min = Point::new(anchor.x - fx * width, anchor.y - fy * height);
+        self.range.max = Point::new(self.range.min.x + width, self.range.min.y + height);
+
+        self.invalidate_and_retile();
+    }
+
+    pub fn get_random_tile(&mut self) -> Option<Tile> {
         if self.tiles.is_empty() {
             None
+        } else if self.priority_center {
+            let closest = self.closest_to_center(self.tiles.iter().map(|tile| &tile.range))?;
+            Some(self.tiles.remove(closest))
         } else {
             let mut rng = thread_rng();
             let len = self.tiles.len();
@@ -201,36 +787,588 @@ impl Server {
         }
     }
 
-    pub fn calculate_resolution(&self, width: u32, height: u32, tiles: u32) -> Resolution {
-        let tile_width = (width / tiles) as u16;
-        let tile_height = (height / tiles) as u16;
+    /// The ranges the current view would be cut into, without queueing anything.
+    pub fn plan_tiles(&self) -> Vec<Range> {
+        Server::generate_tiles(&self.range, &self.config)
+            .into_iter()
+            .map(|tile| tile.range)
+            .collect()
+    }
+
+    fn generate_tiles(range: &Range, config: &ServerConfig) -> Vec<Tile> {
+        let count = config.tiles.max(1);
+        (0..(count * count))
+            .map(|index| Tile::from_grid(index, count, config.width, config.height, range))
+            .collect()
+    }
+}
+
+fn fit_aspect_ratio(range: Range, width: u32, height: u32) -> Range {
+    if width == 0 || height == 0 {
+        return range;
+    }
+
+    let canvas_ratio = width as f64 / height as f64;
+    let span_x = range.max.x - range.min.x;
+    let span_y = range.max.y - range.min.y;
+    let (span_x, span_y) = if span_x / span_y < canvas_ratio {
+        (span_y * canvas_ratio, span_y)
+    } else {
+        (span_x, span_x / canvas_ratio)
+    };
+
+    let center = range.center();
+    Range::new(
+        Point::new(center.x - span_x / 2.0, center.y - span_y / 2.0),
+        Point::new(center.x + span_x / 2.0, center.y + span_y / 2.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::sync::mpsc;
+
+    use crate::models::pixel::pixel_data::PixelData;
+
+    use super::*;
+
+    fn test_server() -> Server {
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(1);
+        let config = ServerConfig::new("localhost".to_string(), 8787, 300, 300, 3);
+        Server::new(config, render_tx)
+    }
+
+    #[test]
+    fn tasks_are_dequeued_in_fifo_order() {
+        let mut server = test_server();
+        let first = server.create_fragment_task().unwrap();
+        let second = server.create_fragment_task().unwrap();
+
+        server.enqueue_task(first.clone());
+        server.enqueue_task(second.clone());
+
+        let origin = |task: FragmentTask| (task.range.min.x, task.range.min.y);
+        assert_eq!(server.dequeue_task().map(origin), Some(origin(first)));
+        assert_eq!(server.dequeue_task().map(origin), Some(origin(second)));
+        assert!(server.dequeue_task().is_none());
+    }
+
+    #[test]
+    fn tiles_cover_the_canvas_without_overlapping() {
+        let mut server = test_server();
+
+        let mut tasks = Vec::new();
+        while let Some(task) = server.create_fragment_task() {
+            tasks.push(task);
+        }
+
+        assert_eq!(tasks.len(), 9);
+        assert_eq!(server.dispatched_tiles.len(), 9);
+
+        let area: u32 = tasks
+            .iter()
+            .map(|task| task.resolution.nx as u32 * task.resolution.ny as u32)
+            .sum();
+        assert_eq!(area, 300 * 300);
+
+        for (i, a) in tasks.iter().enumerate() {
+            assert_eq!((a.resolution.nx, a.resolution.ny), (100, 100));
+            for b in tasks.iter().skip(i + 1) {
+                let overlaps = a.range.min.x < b.range.max.x
+                    && b.range.min.x < a.range.max.x
+                    && a.range.min.y < b.range.max.y
+                    && b.range.min.y < a.range.max.y;
+                assert!(!overlaps, "{:?} overlaps {:?}", a.range, b.range);
+            }
+        }
+    }
+
+    #[test]
+    fn orphaned_tasks_are_reclaimed() {
+        let mut server = test_server();
+        let addr: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+        let task = server.next_task().unwrap();
+
+        server.mark_task_inflight(addr, task.clone());
+        assert_eq!(server.reclaim_stale_tasks(Duration::from_secs(60)), 0);
+        assert!(server.tasks.is_empty());
+
+        assert_eq!(server.reclaim_stale_tasks(Duration::ZERO), 1);
+        assert!(server.inflight_tasks.is_empty());
+        assert_eq!(server.next_task().unwrap().range, task.range);
+    }
+
+    #[test]
+    fn workers_are_looked_up_by_socket_address() {
+        let mut server = test_server();
+        let addr: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+        let other: SocketAddr = "127.0.0.1:4243".parse().unwrap();
+
+        server.register_worker(
+            addr,
+            Worker::new("worker-1".to_string(), 500, "localhost".to_string(), 8787),
+        );
+
+        assert_eq!(server.get_worker(&addr).unwrap().name, "worker-1");
+        assert!(server.get_worker(&other).is_none());
+    }
+
+    #[test]
+    fn panning_by_the_canvas_width_shifts_the_range_by_its_width() {
+        let mut server = test_server();
+        let Range { min, max } = server.range;
+        let width = max.x - min.x;
+
+        server.pan(server.config.width as f64, 0.0);
+
+        assert!((server.range.min.x - (min.x + width)).abs() < 1e-9);
+        assert!((server.range.max.x - (max.x + width)).abs() < 1e-9);
+        assert_eq!(server.range.min.y, min.y);
+        assert_eq!(server.range.max.y, max.y);
+    }
+
+    #[test]
+    fn zooming_at_the_cursor_keeps_the_point_under_it() {
+        let mut server = test_server();
+        let (cursor_x, cursor_y) = (75.0, 210.0);
+        let under_cursor = |server: &Server| {
+            let Range { min, max } = server.range;
+            (
+                min.x + cursor_x / server.config.width as f64 * (max.x - min.x),
+                min.y + cursor_y / server.config.height as f64 * (max.y - min.y),
+            )
+        };
+        let before = under_cursor(&server);
+        let width = server.range.max.x - server.range.min.x;
+
+        server.zoom_at(0.5, cursor_x, cursor_y);
+
+        let after = under_cursor(&server);
+        assert!((before.0 - after.0).abs() < 1e-9);
+        assert!((before.1 - after.1).abs() < 1e-9);
+        assert!((server.range.max.x - server.range.min.x - width * 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zooming_queues_tasks_covering_the_new_range() {
+        let mut server = test_server();
+
+        server.zoom_at(0.5, 150.0, 150.0);
+
+        let range = server.range;
+        assert_eq!(server.tasks.len(), 9);
+        assert!(server.tiles.is_empty());
+        for task in &server.tasks {
+            assert!(
+                task.range.min.x >= range.min.x - 1e-9 && task.range.max.x <= range.max.x + 1e-9
+            );
+            assert!(
+                task.range.min.y >= range.min.y - 1e-9 && task.range.max.y <= range.max.y + 1e-9
+            );
+        }
+        let covered: f64 = server
+            .tasks
+            .iter()
+            .map(|task| {
+                (task.range.max.x - task.range.min.x) * (task.range.max.y - task.range.min.y)
+            })
+            .sum();
+        let area = (range.max.x - range.min.x) * (range.max.y - range.min.y);
+        assert!((covered - area).abs() < 1e-9);
+    }
+
+    #[test]
+    fn results_for_a_previous_view_match_no_task() {
+        let mut server = test_server();
+        let addr: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+        let task = server.next_task().unwrap();
+        server.mark_task_inflight(addr, task.clone());
+        let result = FragmentResult::new(
+            task.id.clone(),
+            task.resolution,
+            task.range,
+            PixelData::new(0, 0),
+        );
+
+        server.zoom_at(0.5, 100.0, 100.0);
+
+        assert!(server.complete_task(&result).is_none());
+    }
+
+    #[test]
+    fn results_complete_the_task_with_their_uuid() {
+        let mut server = test_server();
+        let first_worker: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+        let second_worker: SocketAddr = "127.0.0.1:4243".parse().unwrap();
+        let first = server.next_task().unwrap();
+        let second = server.next_task().unwrap();
+        server.mark_task_inflight(first_worker, first.clone());
+        server.mark_task_inflight(second_worker, second.clone());
+
+        // the uuid wins over the range, which points at the other task
+        let result = FragmentResult::new(
+            second.id.clone(),
+            first.resolution,
+            first.range,
+            PixelData::new(0, 0),
+        )
+        .for_task(second.task_uuid);
 
-        Resolution::new(tile_width, tile_height)
+        let completed = server.complete_task(&result).unwrap();
+        assert_eq!(completed.task_uuid, second.task_uuid);
+        assert_eq!(server.inflight_tasks.len(), 1);
+        assert!(server.inflight_tasks.contains_key(&first_worker));
     }
 
-    pub fn calculate_range(id: u8, tiles: u32, range: &Range) -> Range {
-        let tile_width = (range.max.x - range.min.x) / tiles as f64;
-        let tile_height = (range.max.y - range.min.y) / tiles as f64;
+    #[test]
+    fn tasks_are_created_within_the_iteration_cap() {
+        let mut server = test_server();
+        server.config.max_iteration_cap = 100;
 
-        let x = (id % tiles as u8) as f64;
-        let y = (id / tiles as u8) as f64;
+        let task = server.next_task().unwrap();
 
-        let min = Point::new(range.min.x + x * tile_width, range.min.y + y * tile_height);
-        let max = Point::new(
-            range.min.x + (x + 1.0) * tile_width,
-            range.min.y + (y + 1.0) * tile_height,
+        assert_eq!(task.max_iteration, 100);
+    }
+
+    #[test]
+    fn tile_timings_cover_the_current_view_only() {
+        let mut server = test_server();
+        let task = server.next_task().unwrap();
+
+        server.record_tile_timing(task.range, 42);
+        assert_eq!(
+            server.to_dto().tile_timings,
+            vec![TileTiming {
+                range: task.range,
+                compute_ms: 42
+            }]
         );
 
-        Range::new(min, max)
+        server.zoom_at(0.5, 150.0, 150.0);
+        assert!(server.to_dto().tile_timings.is_empty());
+    }
+
+    #[test]
+    fn new_tasks_use_the_adjusted_max_iteration() {
+        let mut server = test_server();
+        assert_eq!(
+            server.next_task().unwrap().max_iteration,
+            DEFAULT_MAX_ITERATION
+        );
+
+        server.set_max_iteration(1024);
+
+        assert!(server.tasks.iter().all(|task| task.max_iteration == 1024));
+        assert_eq!(server.next_task().unwrap().max_iteration, 1024);
+
+        server.config.max_iteration_cap = 2000;
+        server.set_max_iteration(4096);
+        assert_eq!(server.next_task().unwrap().max_iteration, 2000);
+        server.set_max_iteration(0);
+        assert_eq!(server.next_task().unwrap().max_iteration, 1);
     }
 
-    fn generate_tiles(range: &Range, count: u32) -> Vec<Range> {
-        let mut ranges = Vec::new();
-        for i in 0..(count * count) {
-            let range = Server::calculate_range(i as u8, count, range);
-            ranges.push(range);
+    #[test]
+    fn cycling_to_mandelbrot_frames_it() {
+        let mut server = test_server();
+        server.cycle_fractal();
+        assert_eq!(server.fractals[server.current_fractal].name(), "julia");
+        assert_eq!(
+            server.range,
+            Range::new(Point::new(-1.5, -1.5), Point::new(1.5, 1.5))
+        );
+
+        for _ in 1..server.fractals.len() {
+            server.cycle_fractal();
         }
 
-        ranges
+        assert_eq!(server.fractals[server.current_fractal].name(), "mandelbrot");
+        assert_eq!(
+            server.range,
+            Range::new(Point::new(-2.0, -1.5), Point::new(1.0, 1.5))
+        );
+    }
+
+    #[test]
+    fn view_state_survives_a_round_trip() {
+        let mut server = test_server();
+        server.cycle_fractal();
+        server.zoom_at(0.5, 100.0, 50.0);
+        server.palette.cycle_palette_forward();
+        let path = std::env::temp_dir().join(format!("frakt-state-{}.json", std::process::id()));
+
+        server.save_state(&path).unwrap();
+        let mut restored = test_server();
+        let loaded = restored.load_state(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        loaded.unwrap();
+        assert_eq!(restored.view_state(), server.view_state());
+        assert_eq!(restored.tasks.len(), 9);
+    }
+
+    #[test]
+    fn fractals_are_selected_by_name() {
+        let mut server = test_server();
+
+        assert_eq!(server.set_fractal_by_name("newton_raphson_z4"), Ok(()));
+        assert_eq!(
+            server.fractals[server.current_fractal].name(),
+            "newton_raphson_z4"
+        );
+        assert_eq!(
+            server.range,
+            server.fractals[server.current_fractal].default_range()
+        );
+
+        assert_eq!(
+            server.set_fractal_by_name("unknown"),
+            Err(UnknownFractalError("unknown".to_string()))
+        );
+        assert_eq!(
+            server.fractals[server.current_fractal].name(),
+            "newton_raphson_z4"
+        );
+    }
+
+    #[test]
+    fn dto_exposes_the_workers_and_the_current_range() {
+        let mut server = test_server();
+        let addr: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+        let worker = Worker::new("worker-1".to_string(), 500, "localhost".to_string(), 8787);
+        server.register_worker(addr, worker);
+
+        let json = serde_json::to_value(server.to_dto()).unwrap();
+
+        assert_eq!(json["workers"]["127.0.0.1:4242"]["name"], "worker-1");
+        assert_eq!(json["range"]["min"]["x"], server.range.min.x);
+        assert_eq!(json["range"]["max"]["y"], server.range.max.y);
+        assert_eq!(json["remaining_tiles"], 9);
+    }
+
+    #[test]
+    fn cycling_forward_then_backward_returns_to_the_same_fractal() {
+        let mut server = test_server();
+        let original = server.current_fractal;
+
+        server.cycle_fractal();
+        assert_ne!(server.current_fractal, original);
+        server.cycle_fractal_prev();
+        assert_eq!(server.current_fractal, original);
+
+        server.cycle_fractal_prev();
+        assert_eq!(server.current_fractal, server.fractals.len() - 1);
+        server.cycle_fractal();
+        assert_eq!(server.current_fractal, original);
+    }
+
+    #[test]
+    fn workers_unheard_of_for_too_long_are_stale() {
+        let mut server = test_server();
+        let alive: SocketAddr = "127.0.0.1:4242".parse().unwrap();
+        let gone: SocketAddr = "127.0.0.1:4243".parse().unwrap();
+        let worker = |name: &str| Worker::new(name.to_string(), 500, "localhost".to_string(), 8787);
+        server.register_worker(alive, worker("alive"));
+        server.register_worker(gone, worker("gone"));
+        server
+            .last_seen
+            .insert(gone, Instant::now() - Duration::from_secs(20));
+
+        let mut statuses = server.worker_statuses(Duration::from_secs(15));
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].name, "alive");
+        assert!(!statuses[0].stale);
+        assert_eq!(statuses[1].name, "gone");
+        assert!(statuses[1].stale);
+        assert!(statuses[1].seconds_since_last_seen >= 20.0);
+    }
+
+    #[test]
+    fn completed_fragments_are_counted_per_worker() {
+        let mut server = test_server();
+
+        for _ in 0..3 {
+            server.record_completed_fragment("worker-1", 100);
+        }
+        for _ in 0..2 {
+            server.record_completed_fragment("worker-2", 250);
+        }
+
+        assert_eq!(
+            server.metrics["worker-1"],
+            WorkerMetrics {
+                completed_fragments: 3,
+                total_pixels: 300,
+            }
+        );
+        assert_eq!(
+            server.metrics["worker-2"],
+            WorkerMetrics {
+                completed_fragments: 2,
+                total_pixels: 500,
+            }
+        );
+        assert_eq!(server.to_dto().metrics, server.metrics);
+    }
+
+    #[test]
+    fn heavier_workers_receive_larger_tasks() {
+        let pixels = |task: &FragmentTask| task.resolution.nx as u32 * task.resolution.ny as u32;
+
+        let light = test_server().create_fragment_task_for(100).unwrap();
+        let heavy = test_server().create_fragment_task_for(1000).unwrap();
+
+        assert_eq!(pixels(&light), 100 * 100);
+        assert!(pixels(&heavy) > pixels(&light));
+    }
+
+    #[test]
+    fn widened_tasks_consume_the_queued_tasks_they_cover() {
+        let mut server = test_server();
+        server.invalidate_and_retile();
+
+        let task = server.create_fragment_task_for(10_000).unwrap();
+
+        assert_eq!(task.resolution.nx, 300);
+        assert_eq!(server.tasks.len(), 6);
+        assert!(server
+            .tasks
+            .iter()
+            .all(|queued| queued.range.min.y != task.range.min.y));
+    }
+
+    #[test]
+    fn a_greedy_worker_is_held_to_its_tile_quota() {
+        let mut server = test_server();
+        server.config.worker_tile_quota = Some(2);
+        server.invalidate_and_retile();
+        let greedy: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let modest: SocketAddr = "127.0.0.1:4001".parse().unwrap();
+
+        for _ in 0..5 {
+            let task = server.create_fragment_task_for(10_000).unwrap();
+            server.mark_task_inflight(greedy, task);
+            assert!(server.inflight_tiles(&greedy) <= 2);
+        }
+        let task = server.create_fragment_task_for(10_000).unwrap();
+        server.mark_task_inflight(modest, task);
+
+        // the tasks handed back by the greedy worker were requeued for the others
+        let modest_tiles = server.inflight_tiles(&modest);
+        assert!((1..=2).contains(&modest_tiles));
+        let queued: usize = server
+            .tasks
+            .iter()
+            .map(|task| server.tiles_in(&task.range))
+            .sum();
+        assert_eq!(queued + server.inflight_tiles(&greedy) + modest_tiles, 9);
+    }
+
+    #[test]
+    fn nothing_is_dispatched_while_paused() {
+        let mut server = test_server();
+        let task = server.create_fragment_task().unwrap();
+        server.enqueue_task(task);
+        server.set_paused(true);
+
+        assert!(server.dequeue_task().is_none());
+        assert!(server.create_fragment_task().is_none());
+        assert!(server.create_fragment_task_for(500).is_none());
+
+        server.set_paused(false);
+        assert!(server.dequeue_task().is_some());
+        assert!(server.create_fragment_task().is_some());
+    }
+
+    #[test]
+    fn center_tile_is_dequeued_first_when_prioritized() {
+        let mut server = test_server();
+        server.set_priority_center(true);
+        server.invalidate_and_retile();
+
+        let task = server.dequeue_task().unwrap();
+        let middle = Point::new(
+            (server.range.min.x + server.range.max.x) / 2.0,
+            (server.range.min.y + server.range.max.y) / 2.0,
+        );
+
+        assert!(task.range.min.x <= middle.x && middle.x <= task.range.max.x);
+        assert!(task.range.min.y <= middle.y && middle.y <= task.range.max.y);
+    }
+
+    #[test]
+    fn colliding_worker_names_are_suffixed() {
+        let mut server = test_server();
+        let first: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let second: SocketAddr = "127.0.0.1:4001".parse().unwrap();
+        let worker = || Worker::new("alice".to_string(), 500, "localhost".to_string(), 8787);
+
+        assert_eq!(server.register_worker(first, worker()), "alice");
+        assert_eq!(server.register_worker(second, worker()), "alice-2");
+        // asking again from the same address keeps the name it was given
+        assert_eq!(server.register_worker(second, worker()), "alice-2");
+        assert_eq!(server.get_worker(&second).unwrap().name, "alice-2");
+
+        server.unregister_worker(&first);
+        assert_eq!(server.register_worker(first, worker()), "alice");
+    }
+
+    #[test]
+    fn the_plan_matches_the_tasks_queued_for_the_view() {
+        let mut server = test_server();
+        let plan = server.plan_tiles();
+        assert!(server.tasks.is_empty());
+
+        server.invalidate_and_retile();
+        let mut dequeued = 0;
+        while let Some(task) = server.dequeue_task() {
+            assert!(plan.contains(&task.range));
+            dequeued += 1;
+        }
+
+        assert_eq!(plan.len(), dequeued);
+    }
+
+    #[test]
+    fn the_range_takes_the_proportions_of_a_wide_canvas() {
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(1);
+        let config = ServerConfig::new("localhost".to_string(), 8787, 400, 200, 4);
+        let mut server = Server::new(config, render_tx);
+
+        server.range = Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0));
+        server.enforce_aspect_ratio();
+
+        let ratio = |range: &Range| (range.max.x - range.min.x) / (range.max.y - range.min.y);
+        assert!((ratio(&server.range) - 2.0).abs() < 1e-12);
+        assert_eq!(server.range.min.y, -1.0);
+        assert_eq!(server.range.max.y, 1.0);
+
+        server.zoom_at(0.5, 100.0, 50.0);
+        assert!((ratio(&server.range) - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stepping_the_animation_moves_julia_to_the_next_point() {
+        let mut server = test_server();
+        let path = vec![Complex::new(0.285, 0.013), Complex::new(-0.8, 0.156)];
+        let julia_c = |server: &Server| match &server.fractals[server.current_fractal] {
+            FractalDescriptor::Julia(julia) => (julia.c.re, julia.c.im),
+            other => panic!("expected a Julia set, got {}", other.name()),
+        };
+
+        server.animate_julia(path, 24);
+        assert_eq!(julia_c(&server), (0.285, 0.013));
+
+        server.dequeue_task();
+        assert!(server.step_animation());
+        assert_eq!(julia_c(&server), (-0.8, 0.156));
+        assert_eq!(server.tasks.len(), 9);
+
+        server.step_animation();
+        assert_eq!(julia_c(&server), (0.285, 0.013));
     }
 }
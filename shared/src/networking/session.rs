@@ -0,0 +1,161 @@
+use std::{
+    fs::File,
+    io::{BufWriter, ErrorKind, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::models::fragments::{fragment::Fragment, fragment_task::FragmentTask};
+
+use super::{
+    compression::Compression, error::NetworkingError, read_frame_with_limit,
+    result::NetworkingResult, send_message, DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_READ_TIMEOUT,
+};
+
+/// A fragment result as the server received it, along with the task it answers.
+#[derive(Debug, Clone)]
+pub struct RecordedResult {
+    pub task: FragmentTask,
+    pub json_message: String,
+    /// The signature followed by the pixel intensities.
+    pub data: Vec<u8>,
+}
+
+// the JSON message of a recorded frame, the data of the frame is the one of the result
+#[derive(Serialize, Deserialize)]
+struct SessionEntry {
+    task: serde_json::Value,
+    result: String,
+}
+
+/// Writes every fragment result the server receives to a file, to be replayed later with
+/// [`read_session`]. Each result is kept in a frame of its own, as it came off the connection.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl SessionRecorder {
+    /// Starts a session in `path`, replacing the file if it exists.
+    pub fn create(path: &Path) -> NetworkingResult<Self> {
+        Ok(Self {
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    pub async fn record(
+        &self,
+        task: &FragmentTask,
+        json_message: &str,
+        data: &[u8],
+    ) -> NetworkingResult<()> {
+        let entry = SessionEntry {
+            task: task.to_json()?,
+            result: json_message.to_string(),
+        };
+        let mut frame = Vec::new();
+        send_message(
+            &mut frame,
+            serde_json::to_string(&entry)?.as_bytes(),
+            Some(data),
+            Compression::None,
+        )
+        .await?;
+
+        // flushed right away, a session is mostly recorded to look into a crash
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&frame)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the results recorded by a [`SessionRecorder`], in the order they were received. A
+/// session cut short ends with the last complete result.
+pub async fn read_session(path: &Path) -> NetworkingResult<Vec<RecordedResult>> {
+    let mut file = tokio::io::BufReader::new(tokio::fs::File::open(path).await?);
+
+    let mut results = Vec::new();
+    loop {
+        let frame =
+            match read_frame_with_limit(&mut file, DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_READ_TIMEOUT)
+                .await
+            {
+                Ok(frame) => frame,
+                Err(NetworkingError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            };
+
+        let entry: SessionEntry = serde_json::from_str(&frame.json_message())?;
+        let task = match FragmentTask::from_json(&entry.task.to_string()) {
+            Ok(task) => task,
+            Err(e) => {
+                warn!(
+                    "Skipping a recorded result whose task does not parse: {}",
+                    e
+                );
+                continue;
+            }
+        };
+        results.push(RecordedResult {
+            task,
+            json_message: entry.result,
+            data: frame.data().to_vec(),
+        });
+    }
+
+    debug!(
+        "Read {} recorded result(s) from {}",
+        results.len(),
+        path.display()
+    );
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        fractal::{fractal_descriptor::FractalDescriptor, mandelbrot::Mandelbrot},
+        point::Point,
+        range::Range,
+        resolution::Resolution,
+        u8_data::U8Data,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn recorded_results_are_read_back_in_order() {
+        let path = std::env::temp_dir().join(format!("frakt-session-{}.bin", std::process::id()));
+        let task = FragmentTask::new(
+            U8Data::new(0, 16),
+            FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            64,
+            Resolution::new(4, 4),
+            Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)),
+        );
+
+        let recorder = SessionRecorder::create(&path).unwrap();
+        for index in 0..3u8 {
+            let json = format!(r#"{{"FragmentResult":{{"index":{}}}}}"#, index);
+            recorder.record(&task, &json, &[index; 24]).await.unwrap();
+        }
+        drop(recorder);
+        let session = read_session(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(session.len(), 3);
+        for (index, recorded) in session.iter().enumerate() {
+            assert_eq!(recorded.task.task_uuid, task.task_uuid);
+            assert_eq!(recorded.task.range, task.range);
+            assert_eq!(
+                recorded.json_message,
+                format!(r#"{{"FragmentResult":{{"index":{}}}}}"#, index)
+            );
+            assert_eq!(recorded.data, vec![index as u8; 24]);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signatures are HMAC-SHA256 tags truncated to their first 16 bytes, matching the
+/// size of the `id` section of the frakt protocol.
+pub const SIGNATURE_SIZE: usize = 16;
+
+pub type Signature = [u8; SIGNATURE_SIZE];
+
+fn mac_over(key: &[u8], json_message: &[u8], data: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(json_message);
+    mac.update(data);
+    mac
+}
+
+pub fn sign_payload(key: &[u8], json_message: &[u8], data: &[u8]) -> Signature {
+    let tag = mac_over(key, json_message, data).finalize().into_bytes();
+    let mut signature = [0u8; SIGNATURE_SIZE];
+    signature.copy_from_slice(&tag[..SIGNATURE_SIZE]);
+    signature
+}
+
+/// Signature of a payload whose data arrives a piece at a time.
+#[derive(Clone)]
+pub struct PayloadMac(HmacSha256);
+
+impl PayloadMac {
+    pub fn new(key: &[u8], json_message: &[u8]) -> Self {
+        Self(mac_over(key, json_message, &[]))
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Whether `signature` signs the JSON message and all the data given so far.
+    pub fn verify(&self, signature: &[u8]) -> bool {
+        signature.len() == SIGNATURE_SIZE && self.0.clone().verify_truncated_left(signature).is_ok()
+    }
+}
+
+pub fn verify_payload(key: &[u8], json_message: &[u8], data: &[u8], signature: &[u8]) -> bool {
+    signature.len() == SIGNATURE_SIZE
+        && mac_over(key, json_message, data)
+            .verify_truncated_left(signature)
+            .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4231, test case 2
+    const KEY: &[u8] = b"Jefe";
+    const EXPECTED: Signature = [
+        0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95, 0x75,
+        0xc7,
+    ];
+
+    #[test]
+    fn signs_a_known_vector() {
+        let signature = sign_payload(KEY, b"what do ya want ", b"for nothing?");
+        assert_eq!(signature, EXPECTED);
+    }
+
+    #[test]
+    fn verifies_only_matching_payloads() {
+        let json = b"what do ya want ";
+
+        assert!(verify_payload(KEY, json, b"for nothing?", &EXPECTED));
+        assert!(!verify_payload(KEY, json, b"for everything?", &EXPECTED));
+        assert!(!verify_payload(b"Jeff", json, b"for nothing?", &EXPECTED));
+        assert!(!verify_payload(KEY, json, b"for nothing?", &EXPECTED[..8]));
+    }
+}
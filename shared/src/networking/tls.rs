@@ -0,0 +1,181 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc, time::SystemTime};
+
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore, ServerName,
+};
+use rustls_pemfile::Item;
+pub use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use super::{error::NetworkingError, result::NetworkingResult};
+
+/// Wraps the connections accepted by the server, presenting the PEM certificate chain at
+/// `cert_path` signed by the key at `key_path`.
+pub fn acceptor(cert_path: &Path, key_path: &Path) -> NetworkingResult<TlsAcceptor> {
+    let certs = read_certs(cert_path)?;
+    let key = read_private_key(key_path)?;
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| NetworkingError::Tls(e.to_string()))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Opens the worker side of the connections. The server is trusted if its certificate is
+/// signed by the PEM CA at `ca_path`, or by a well-known root when none is given.
+/// `insecure` trusts any certificate, which only makes sense against a test server.
+pub fn connector(ca_path: Option<&Path>, insecure: bool) -> NetworkingResult<TlsConnector> {
+    let builder = ClientConfig::builder().with_safe_defaults();
+    let config = if insecure {
+        builder
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate))
+            .with_no_client_auth()
+    } else {
+        builder
+            .with_root_certificates(root_store(ca_path)?)
+            .with_no_client_auth()
+    };
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Name the server certificate is checked against, a host name or an IP address.
+pub fn server_name(address: &str) -> NetworkingResult<ServerName> {
+    ServerName::try_from(address)
+        .map_err(|_| NetworkingError::Tls(format!("`{}` is not a valid server name", address)))
+}
+
+fn root_store(ca_path: Option<&Path>) -> NetworkingResult<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    match ca_path {
+        Some(path) => {
+            for cert in read_certs(path)? {
+                roots
+                    .add(&cert)
+                    .map_err(|e| NetworkingError::Tls(e.to_string()))?;
+            }
+        }
+        None => roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        })),
+    }
+    Ok(roots)
+}
+
+fn read_certs(path: &Path) -> NetworkingResult<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))?;
+    if certs.is_empty() {
+        return Err(NetworkingError::Tls(format!(
+            "no certificate in {}",
+            path.display()
+        )));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_private_key(path: &Path) -> NetworkingResult<PrivateKey> {
+    let mut reader = BufReader::new(File::open(path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        if let Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) = item {
+            return Ok(PrivateKey(key));
+        }
+    }
+    Err(NetworkingError::Tls(format!(
+        "no private key in {}",
+        path.display()
+    )))
+}
+
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::networking::{compression::Compression, read_message_raw, send_message};
+
+    use super::*;
+
+    // a self-signed certificate for localhost, its PEM files named after `test`
+    fn self_signed(test: &str) -> (PathBuf, PathBuf) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let directory = std::env::temp_dir();
+        let cert_path = directory.join(format!("frakt-{}-{}.crt", test, std::process::id()));
+        let key_path = directory.join(format!("frakt-{}-{}.key", test, std::process::id()));
+        fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        (cert_path, key_path)
+    }
+
+    async fn exchange(acceptor: TlsAcceptor, connector: TlsConnector) -> NetworkingResult<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(socket).await?;
+            read_message_raw(&mut stream)
+                .await
+                .map(|message| message.json_message)
+                .map_err(|e| std::io::Error::other(e.to_string()))
+        });
+
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector.connect(server_name("localhost")?, socket).await?;
+        send_message(&mut stream, b"{\"hello\":1}", None, Compression::None).await?;
+
+        Ok(server.await.unwrap()?)
+    }
+
+    #[tokio::test]
+    async fn a_framed_message_goes_through_a_tls_connection() {
+        let (cert_path, key_path) = self_signed("trusted");
+        let acceptor = acceptor(&cert_path, &key_path).unwrap();
+        let connector = connector(Some(&cert_path), false).unwrap();
+
+        let message = exchange(acceptor, connector).await.unwrap();
+
+        assert_eq!(message, "{\"hello\":1}");
+    }
+
+    #[tokio::test]
+    async fn an_untrusted_certificate_is_refused_unless_insecure() {
+        let (cert_path, key_path) = self_signed("untrusted");
+        let (other_cert_path, _) = self_signed("other");
+
+        let refused = exchange(
+            acceptor(&cert_path, &key_path).unwrap(),
+            connector(Some(&other_cert_path), false).unwrap(),
+        )
+        .await;
+        let accepted = exchange(
+            acceptor(&cert_path, &key_path).unwrap(),
+            connector(None, true).unwrap(),
+        )
+        .await;
+
+        assert!(refused.is_err());
+        assert_eq!(accepted.unwrap(), "{\"hello\":1}");
+    }
+}
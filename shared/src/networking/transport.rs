@@ -0,0 +1,239 @@
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use log::warn;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+};
+
+use super::result::NetworkingResult;
+
+/// Where the server listens and the workers connect, both ends speak the same frames on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// A `host:port` TCP address.
+    Tcp { addr: String },
+    /// A UNIX domain socket, for a worker running on the same host as the server.
+    Unix { path: PathBuf },
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Tcp { addr } => write!(f, "{}", addr),
+            Transport::Unix { path } => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Transport {
+    pub async fn connect(&self) -> NetworkingResult<Stream> {
+        match self {
+            Transport::Tcp { addr } => Ok(Stream::Tcp(TcpStream::connect(addr).await?)),
+            #[cfg(unix)]
+            Transport::Unix { path } => Ok(Stream::Unix(UnixStream::connect(path).await?)),
+            #[cfg(not(unix))]
+            Transport::Unix { .. } => Err(unix_unsupported().into()),
+        }
+    }
+
+    /// Starts listening, replacing the socket file a previous server may have left behind.
+    pub async fn bind(&self) -> NetworkingResult<Listener> {
+        match self {
+            Transport::Tcp { addr } => Ok(Listener::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            Transport::Unix { path } => {
+                if path.exists() {
+                    warn!("Replacing the existing socket file {}", path.display());
+                    std::fs::remove_file(path)?;
+                }
+                Ok(Listener::Unix {
+                    listener: UnixListener::bind(path)?,
+                    path: path.clone(),
+                    next_peer: 0,
+                })
+            }
+            #[cfg(not(unix))]
+            Transport::Unix { .. } => Err(unix_unsupported().into()),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn unix_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "UNIX domain sockets are not supported on this platform",
+    )
+}
+
+/// A connection over either transport.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        path: PathBuf,
+        next_peer: u16,
+    },
+}
+
+impl Listener {
+    /// Accepts the next connection along with the address its worker is known by. UNIX peers
+    /// have none, each connection is given a made-up `0.0.0.0:n` instead.
+    pub async fn accept(&mut self) -> io::Result<(Stream, SocketAddr)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((Stream::Tcp(stream), addr))
+            }
+            #[cfg(unix)]
+            Listener::Unix {
+                listener,
+                next_peer,
+                ..
+            } => {
+                let (stream, _) = listener.accept().await?;
+                *next_peer = next_peer.wrapping_add(1);
+                Ok((
+                    Stream::Unix(stream),
+                    SocketAddr::from(([0, 0, 0, 0], *next_peer)),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix { path, .. } = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::{
+        env,
+        models::{
+            fragments::{fragment::Fragment, fragment_result::FragmentResult},
+            pixel::pixel_data::PixelData,
+            point::Point,
+            range::Range,
+            resolution::Resolution,
+            u8_data::U8Data,
+        },
+        networking::{
+            compression::Compression,
+            read_message_raw, send_result,
+            signature::{verify_payload, SIGNATURE_SIZE},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn a_fragment_result_round_trips_over_a_unix_socket() {
+        let path = std::env::temp_dir().join(format!("frakt-{}.sock", std::process::id()));
+        let transport = Transport::Unix { path: path.clone() };
+        let mut listener = transport.bind().await.unwrap();
+        let result = FragmentResult::new(
+            U8Data::new(0, 16),
+            Resolution::new(2, 2),
+            Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)),
+            PixelData::new(16, 4),
+        );
+        let data: Vec<u8> = (0..32).collect();
+
+        let sent = {
+            let json = serde_json::to_string(&result.to_json().unwrap()).unwrap();
+            let data = data.clone();
+            tokio::spawn(async move {
+                let mut stream = transport.connect().await.unwrap();
+                send_result(&mut stream, &json, &data, Compression::None)
+                    .await
+                    .unwrap();
+            })
+        };
+        let (mut stream, peer) = listener.accept().await.unwrap();
+        let message = read_message_raw(&mut stream).await.unwrap();
+        sent.await.unwrap();
+
+        let received = FragmentResult::from_json(&message.json_message).unwrap();
+        let (signature, received_data) = message.data.split_at(SIGNATURE_SIZE);
+        assert!(verify_payload(
+            env::secret(),
+            message.json_message.as_bytes(),
+            received_data,
+            signature
+        ));
+        assert_eq!(received.range, result.range);
+        assert_eq!(received_data, data.as_slice());
+        assert_eq!(peer, SocketAddr::from(([0, 0, 0, 0], 1)));
+
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
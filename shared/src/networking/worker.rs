@@ -1,11 +1,89 @@
 use serde::{Deserialize, Serialize};
 
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dtos::benchmark_report::BenchmarkReport, models::fragments::fragment_task::FragmentTask,
+};
+
+use super::{
+    compression::Compression, transport::Transport, DEFAULT_HEARTBEAT_INTERVAL,
+    DEFAULT_MAX_ITERATION_CAP, DEFAULT_MAX_RETRIES, DEFAULT_MAX_RETRY_DELAY, DEFAULT_READ_TIMEOUT,
+    DEFAULT_RETRY_DELAY, DEFAULT_TILE_CACHE_CAPACITY,
+};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Worker {
     pub name: String,
     pub maximal_work_load: u32,
     pub address: String,
     pub port: u16,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout: Duration,
+    /// Pause between two tasks, mostly useful to slow a worker down while debugging.
+    #[serde(default)]
+    pub task_delay: Duration,
+    #[serde(default = "default_heartbeat_interval")]
+    pub heartbeat_interval: Duration,
+    /// Results kept to answer a task computed before without computing it again.
+    #[serde(default = "default_tile_cache_capacity")]
+    pub tile_cache_capacity: usize,
+    /// Tasks asking for more iterations are lowered to this ceiling.
+    #[serde(default = "default_max_iteration_ceiling")]
+    pub max_iteration_ceiling: u32,
+    /// Wait before reconnecting after an error, doubled with each error in a row up to
+    /// `max_retry_delay`. The worker gives up after `max_retries` errors in a row, a server
+    /// without tasks is polled at the same pace but never given up on.
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay: Duration,
+    #[serde(default = "default_max_retry_delay")]
+    pub max_retry_delay: Duration,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Connects over TLS, trusting the server if `tls_ca` signed its certificate, or a
+    /// well-known root when unset. `tls_insecure` trusts any certificate.
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub tls_ca: Option<PathBuf>,
+    #[serde(default)]
+    pub tls_insecure: bool,
+    /// Connects to the server through this UNIX domain socket instead of `address:port`.
+    #[serde(default)]
+    pub unix_socket: Option<PathBuf>,
+}
+
+fn default_read_timeout() -> Duration {
+    DEFAULT_READ_TIMEOUT
+}
+
+fn default_heartbeat_interval() -> Duration {
+    DEFAULT_HEARTBEAT_INTERVAL
+}
+
+fn default_tile_cache_capacity() -> usize {
+    DEFAULT_TILE_CACHE_CAPACITY
+}
+
+fn default_max_iteration_ceiling() -> u32 {
+    DEFAULT_MAX_ITERATION_CAP
+}
+
+fn default_retry_delay() -> Duration {
+    DEFAULT_RETRY_DELAY
+}
+
+fn default_max_retry_delay() -> Duration {
+    DEFAULT_MAX_RETRY_DELAY
+}
+
+fn default_max_retries() -> u32 {
+    DEFAULT_MAX_RETRIES
 }
 
 impl Worker {
@@ -15,6 +93,72 @@ impl Worker {
             maximal_work_load,
             address,
             port,
+            compression: Compression::None,
+            read_timeout: default_read_timeout(),
+            task_delay: Duration::ZERO,
+            heartbeat_interval: default_heartbeat_interval(),
+            tile_cache_capacity: default_tile_cache_capacity(),
+            max_iteration_ceiling: default_max_iteration_ceiling(),
+            retry_delay: default_retry_delay(),
+            max_retry_delay: default_max_retry_delay(),
+            max_retries: default_max_retries(),
+            tls: false,
+            tls_ca: None,
+            tls_insecure: false,
+            unix_socket: None,
         }
     }
+
+    pub fn transport(&self) -> Transport {
+        match &self.unix_socket {
+            Some(path) => Transport::Unix { path: path.clone() },
+            None => Transport::Tcp {
+                addr: format!("{}:{}", self.address, self.port),
+            },
+        }
+    }
+
+    /// Performs `task` `runs` times locally and reports the achieved throughput.
+    pub fn benchmark(
+        task: &FragmentTask,
+        runs: u32,
+    ) -> Result<BenchmarkReport, Box<dyn std::error::Error>> {
+        let pixels_per_run = task.resolution.nx as u64 * task.resolution.ny as u64;
+        let mut run_times = Vec::with_capacity(runs as usize);
+
+        let start = Instant::now();
+        for _ in 0..runs {
+            let run_start = Instant::now();
+            task.perform()?;
+            run_times.push(run_start.elapsed());
+        }
+
+        Ok(BenchmarkReport::new(
+            pixels_per_run,
+            &run_times,
+            start.elapsed(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_are_not_delayed_by_default() {
+        let worker = Worker::new("worker".to_string(), 500, "localhost".to_string(), 8787);
+
+        assert_eq!(worker.task_delay, Duration::ZERO);
+    }
+
+    #[test]
+    fn missing_task_delay_deserializes_to_zero() {
+        let worker: Worker = serde_json::from_str(
+            r#"{"name":"worker","maximal_work_load":500,"address":"localhost","port":8787}"#,
+        )
+        .unwrap();
+
+        assert_eq!(worker.task_delay, Duration::ZERO);
+    }
 }
@@ -1,8 +1,13 @@
+use std::fmt;
+
 pub type Rgb = (u8, u8, u8);
 pub type Vec3 = (f64, f64, f64);
 
+/// Painted where a fractal produced a NaN or infinite value, so it stands out.
+pub const DEFAULT_ERROR_COLOR: Rgb = (255, 0, 255);
+
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorPalette {
     Classic,
     Inverted,
@@ -14,59 +19,141 @@ pub enum ColorPalette {
     NewtonRapshon,
     Custom1,
     Custom2,
+    HsvRainbow,
 }
 
 impl ColorPalette {
     // NOTE: this should be updated whenever a field is added
-    // TODO: look if there is a way to add a Count entry in the enum to track it directly
-    fn variant_count() -> u8 {
-        10
-    }
-
-    fn from_index(index: u8) -> Option<ColorPalette> {
-        match index {
-            0 => Some(ColorPalette::Classic),
-            1 => Some(ColorPalette::Inverted),
-            2 => Some(ColorPalette::Viridis),
-            3 => Some(ColorPalette::Magma),
-            4 => Some(ColorPalette::Plasma),
-            5 => Some(ColorPalette::Inferno),
-            6 => Some(ColorPalette::Grayscale),
-            7 => Some(ColorPalette::NewtonRapshon),
-            8 => Some(ColorPalette::Custom1),
-            9 => Some(ColorPalette::Custom2),
-            _ => None,
+    pub const ALL: [ColorPalette; 11] = [
+        ColorPalette::Classic,
+        ColorPalette::Inverted,
+        ColorPalette::Viridis,
+        ColorPalette::Plasma,
+        ColorPalette::Magma,
+        ColorPalette::Inferno,
+        ColorPalette::Grayscale,
+        ColorPalette::NewtonRapshon,
+        ColorPalette::Custom1,
+        ColorPalette::Custom2,
+        ColorPalette::HsvRainbow,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorPalette::Classic => "classic",
+            ColorPalette::Inverted => "inverted",
+            ColorPalette::Viridis => "viridis",
+            ColorPalette::Plasma => "plasma",
+            ColorPalette::Magma => "magma",
+            ColorPalette::Inferno => "inferno",
+            ColorPalette::Grayscale => "grayscale",
+            ColorPalette::NewtonRapshon => "newton_raphson",
+            ColorPalette::Custom1 => "custom1",
+            ColorPalette::Custom2 => "custom2",
+            ColorPalette::HsvRainbow => "hsv_rainbow",
         }
     }
+
+    /// Looks a palette up by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<ColorPalette> {
+        Self::ALL
+            .into_iter()
+            .find(|palette| palette.name().eq_ignore_ascii_case(name))
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPaletteError(pub String);
+
+impl fmt::Display for UnknownPaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown color palette: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPaletteError {}
+
+#[derive(Debug, Clone)]
 pub struct PaletteHandler {
-    pub current_palette: ColorPalette,
+    palettes: Vec<ColorPalette>,
+    current: usize,
+    /// Applied to every channel after the palette, 1.0 leaves colors untouched.
+    pub gamma: f64,
+    /// Painted for non-finite values of `t`.
+    pub error_color: Rgb,
 }
 
 impl PaletteHandler {
     pub fn new() -> Self {
+        PaletteHandler::with_palettes(ColorPalette::ALL.to_vec())
+    }
+
+    /// Cycles through `palettes` only, in the given order, starting with the first one.
+    pub fn with_palettes(palettes: Vec<ColorPalette>) -> Self {
+        assert!(!palettes.is_empty(), "a palette handler needs a palette");
         PaletteHandler {
-            current_palette: ColorPalette::Classic, 
+            palettes,
+            current: 0,
+            gamma: 1.0,
+            error_color: DEFAULT_ERROR_COLOR,
         }
     }
 
+    /// Starts on a palette picked from `seed`, so that renders sharing a seed share their
+    /// colors. Colors only depend on `t`, the current palette and the gamma.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut handler = PaletteHandler::new();
+        handler.current = (seed % handler.palettes.len() as u64) as usize;
+        handler
+    }
+
+    pub fn current_palette(&self) -> ColorPalette {
+        self.palettes[self.current]
+    }
+
+    pub fn palettes(&self) -> &[ColorPalette] {
+        &self.palettes
+    }
+
     pub fn cycle_palette_forward(&mut self) {
-        let palette_count = ColorPalette::variant_count();
-        let current_index = self.current_palette as u8;
-        let next_index = (current_index + 1) % palette_count;
-        self.current_palette = ColorPalette::from_index(next_index).unwrap();
+        self.current = (self.current + 1) % self.palettes.len();
     }
 
     pub fn cycle_palette_backward(&mut self) {
-        let palette_count = ColorPalette::variant_count();
-        let current_index = self.current_palette as u8;
-        let next_index = (current_index + palette_count - 1) % palette_count;
-        self.current_palette = ColorPalette::from_index(next_index).unwrap();
+        self.current = (self.current + self.palettes.len() - 1) % self.palettes.len();
+    }
+
+    pub fn set_palette_by_name(&mut self, name: &str) -> Result<(), UnknownPaletteError> {
+        let index = self
+            .palettes
+            .iter()
+            .position(|palette| palette.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownPaletteError(name.to_string()))?;
+        self.current = index;
+        Ok(())
     }
 
+    /// Colors `t`, clamped to `[0, 1]`, or returns the error color if it is not finite.
     pub fn calculate_color(&self, t: f64) -> Rgb {
-        match self.current_palette {
+        if !t.is_finite() {
+            return self.error_color;
+        }
+
+        let color = self.palette_color(t.clamp(0.0, 1.0));
+        if self.gamma == 1.0 {
+            return color;
+        }
+
+        let correct = |channel: u8| {
+            (255.0 * (channel as f64 / 255.0).powf(1.0 / self.gamma))
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        (correct(color.0), correct(color.1), correct(color.2))
+    }
+
+    fn palette_color(&self, t: f64) -> Rgb {
+        match self.current_palette() {
             ColorPalette::Classic => self.classic_palette(t),
             ColorPalette::Inverted => self.inverted_palette(t),
             ColorPalette::Grayscale => self.grayscale_palette(t),
@@ -75,6 +162,7 @@ impl PaletteHandler {
             ColorPalette::Magma => self.magma_palette(t),
             ColorPalette::Inferno => self.inferno_palette(t),
             ColorPalette::NewtonRapshon => self.newton_raphson_palette(t),
+            ColorPalette::HsvRainbow => self.hsv_rainbow_palette(t),
             _ => self.custom_palette(t),
         }
     }
@@ -122,7 +210,7 @@ impl PaletteHandler {
             (x, y, z)
         }
 
-        match self.current_palette {
+        match self.current_palette() {
             ColorPalette::Custom1 => self.calculate_custom_palette(
                 t,
                 vec3(0.5, 0.5, 0.5),
@@ -300,6 +388,11 @@ impl PaletteHandler {
         (r as u8, g as u8, b as u8)
     }
 
+    // sweeps the whole hue circle at full saturation and value
+    pub fn hsv_rainbow_palette(&self, t: f64) -> Rgb {
+        hsv_to_rgb(t.clamp(0.0, 1.0) * 360.0, 1.0, 1.0)
+    }
+
     fn calculate_color_from_coefficients(
         &self,
         r_coeffs: (f64, f64, f64, f64, f64, f64, f64),
@@ -320,3 +413,159 @@ impl PaletteHandler {
         )
     }
 }
+
+/// Converts a color from HSV, with `hue` in degrees and `saturation`/`value` in `[0, 1]`.
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgb {
+    let hue = hue.rem_euclid(360.0);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = value - chroma;
+
+    let (r, g, b) = match hue {
+        h if h < 60.0 => (chroma, x, 0.0),
+        h if h < 120.0 => (x, chroma, 0.0),
+        h if h < 180.0 => (0.0, chroma, x),
+        h if h < 240.0 => (0.0, x, chroma),
+        h if h < 300.0 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let to_channel = |c: f64| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (to_channel(r), to_channel(g), to_channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hsv_rainbow_sweeps_the_hue_circle() {
+        let palette = PaletteHandler::with_palettes(vec![ColorPalette::HsvRainbow]);
+
+        assert_eq!(palette.calculate_color(0.0), (255, 0, 0));
+        assert_eq!(palette.calculate_color(1.0 / 3.0), (0, 255, 0));
+        assert_eq!(palette.calculate_color(2.0 / 3.0), (0, 0, 255));
+        assert_eq!(palette.calculate_color(1.0), (255, 0, 0));
+    }
+
+    #[test]
+    fn hsv_conversion_stays_in_channel_range() {
+        for step in 0..=720 {
+            let hue = step as f64 * 0.5;
+            for (saturation, value) in [(1.0, 1.0), (0.5, 0.75), (0.0, 1.0), (1.0, 0.0)] {
+                let (r, g, b) = hsv_to_rgb(hue, saturation, value);
+                let max = r.max(g).max(b) as f64;
+                assert!(
+                    (max - value * 255.0).abs() <= 1.0,
+                    "hue {}: {:?}",
+                    hue,
+                    (r, g, b)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cycling_wraps_around_in_both_directions() {
+        let mut palette = PaletteHandler::new();
+        assert_eq!(palette.current_palette(), ColorPalette::Classic);
+
+        palette.cycle_palette_backward();
+        assert_eq!(palette.current_palette(), ColorPalette::HsvRainbow);
+
+        palette.cycle_palette_forward();
+        assert_eq!(palette.current_palette(), ColorPalette::Classic);
+
+        for _ in 0..ColorPalette::ALL.len() {
+            palette.cycle_palette_forward();
+        }
+        assert_eq!(palette.current_palette(), ColorPalette::Classic);
+    }
+
+    #[test]
+    fn every_palette_is_visited_once_per_cycle() {
+        let mut palette = PaletteHandler::new();
+        let mut visited = Vec::new();
+        for _ in 0..ColorPalette::ALL.len() {
+            visited.push(palette.current_palette());
+            palette.cycle_palette_forward();
+        }
+
+        assert_eq!(visited, ColorPalette::ALL);
+    }
+
+    #[test]
+    fn seeded_handlers_agree_on_colors() {
+        let first = PaletteHandler::with_seed(42);
+        let second = PaletteHandler::with_seed(42);
+
+        assert_eq!(first.current_palette(), second.current_palette());
+        assert_eq!(
+            PaletteHandler::with_seed(ColorPalette::ALL.len() as u64).current_palette(),
+            ColorPalette::Classic
+        );
+        for step in 0..=100 {
+            let t = step as f64 / 100.0;
+            assert_eq!(first.calculate_color(t), second.calculate_color(t));
+        }
+    }
+
+    #[test]
+    fn non_finite_values_get_the_error_color() {
+        let mut palette = PaletteHandler::new();
+
+        for t in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(palette.calculate_color(t), DEFAULT_ERROR_COLOR);
+        }
+
+        palette.error_color = (1, 2, 3);
+        assert_eq!(palette.calculate_color(f64::NAN), (1, 2, 3));
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let palette = PaletteHandler::with_palettes(vec![ColorPalette::Grayscale]);
+
+        assert_eq!(palette.calculate_color(-1.0), palette.calculate_color(0.0));
+        assert_eq!(palette.calculate_color(2.0), palette.calculate_color(1.0));
+        assert_eq!(palette.calculate_color(2.0), (255, 255, 255));
+    }
+
+    #[test]
+    fn palettes_are_looked_up_by_name() {
+        let mut palette = PaletteHandler::new();
+
+        assert_eq!(palette.set_palette_by_name("Viridis"), Ok(()));
+        assert_eq!(palette.current_palette(), ColorPalette::Viridis);
+
+        assert_eq!(
+            palette.set_palette_by_name("sepia"),
+            Err(UnknownPaletteError("sepia".to_string()))
+        );
+        assert_eq!(palette.current_palette(), ColorPalette::Viridis);
+    }
+
+    #[test]
+    fn gamma_brightens_mid_tones() {
+        let mut palette = PaletteHandler::with_palettes(vec![ColorPalette::Grayscale]);
+        let (linear, _, _) = palette.calculate_color(0.5);
+
+        palette.gamma = 2.2;
+        let (corrected, _, _) = palette.calculate_color(0.5);
+
+        assert_eq!(linear, 127);
+        assert!(corrected > linear);
+        assert_eq!(palette.calculate_color(0.0), (0, 0, 0));
+        assert_eq!(palette.calculate_color(1.0), (255, 255, 255));
+    }
+
+    #[test]
+    fn unit_gamma_is_a_no_op() {
+        let palette = PaletteHandler::new();
+
+        for step in 0..=10 {
+            let t = step as f64 / 10.0;
+            assert_eq!(palette.calculate_color(t), palette.palette_color(t));
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GraphicsError {
+    /// No display to open a window on, e.g. on a headless machine.
+    #[error("[Event Loop Error] {0}")]
+    EventLoop(String),
+    #[error("[Window Error] {0}")]
+    Window(#[from] winit::error::OsError),
+    #[error("[Thread Error] {0}")]
+    Thread(#[from] std::io::Error),
+    #[error("[Pixels Error] {0}")]
+    Pixels(#[from] pixels::Error),
+}
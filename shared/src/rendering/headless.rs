@@ -0,0 +1,306 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use image::{ImageError, ImageResult, Rgb, RgbImage};
+use log::{debug, info, warn};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    dtos::rendering_data::RenderingData, models::range::Range, networking::server::Server,
+};
+
+use super::{fragment_origin, fragment_pixel_index};
+
+/// Scale applied to the view between two exported frames when nothing is animated.
+const EXPORT_ZOOM_STEP: f64 = 0.9;
+
+/// Copies the pixels of a fragment into `image`, which shows the `view` range of the fractal.
+pub fn draw_fragment(image: &mut RgbImage, data: &RenderingData, view: &Range) {
+    let result = &data.result;
+    let (start_x, start_y) = fragment_origin(&result.range, view, image.width(), image.height());
+
+    for y in 0..result.resolution.ny {
+        for x in 0..result.resolution.nx {
+            let (image_x, image_y) = (start_x + x as i64, start_y + y as i64);
+            if !(0..image.width() as i64).contains(&image_x)
+                || !(0..image.height() as i64).contains(&image_y)
+            {
+                continue;
+            }
+            let (image_x, image_y) = (image_x as u32, image_y as u32);
+
+            if let Some(&(r, g, b)) =
+                data.pixels
+                    .get(fragment_pixel_index(x, y, result.resolution.nx))
+            {
+                image.put_pixel(image_x, image_y, Rgb([r, g, b]));
+            }
+        }
+    }
+}
+
+/// Waits for every pixel of the current view to come back, then saves the frame as a PNG.
+pub async fn render_to_file(
+    server: Arc<Mutex<Server>>,
+    rendering_data_receiver: &mut Receiver<RenderingData>,
+    path: &Path,
+) -> ImageResult<()> {
+    let (width, height, view) = {
+        let server = server.lock().unwrap();
+        (server.config.width, server.config.height, server.range)
+    };
+
+    // fragments may span several tiles, so progress is counted in pixels
+    let mut remaining = width as u64 * height as u64;
+    info!("Rendering {} pixel(s) to {}", remaining, path.display());
+    let mut image = RgbImage::new(width, height);
+    while remaining > 0 {
+        let Some(data) = rendering_data_receiver.recv().await else {
+            warn!("Rendering channel closed with {} pixel(s) left", remaining);
+            break;
+        };
+        draw_fragment(&mut image, &data, &view);
+        let resolution = data.result.resolution;
+        remaining = remaining.saturating_sub(resolution.nx as u64 * resolution.ny as u64);
+        debug!("{} pixel(s) left to render", remaining);
+    }
+
+    image.save(path)?;
+    info!("Saved the rendered frame to {}", path.display());
+    Ok(())
+}
+
+/// Renders `frames` frames into numbered PNGs in `directory`, each written once all of its
+/// tiles came back. Frames follow the Julia animation if one runs, or zoom into the center.
+pub async fn export_frames(
+    server: Arc<Mutex<Server>>,
+    rendering_data_receiver: &mut Receiver<RenderingData>,
+    directory: &Path,
+    frames: u32,
+) -> ImageResult<Vec<PathBuf>> {
+    fs::create_dir_all(directory).map_err(ImageError::IoError)?;
+
+    let mut paths = Vec::new();
+    for frame in 0..frames {
+        let path = directory.join(format!("frame_{:04}.png", frame));
+        render_to_file(server.clone(), rendering_data_receiver, &path).await?;
+        paths.push(path);
+
+        if frame + 1 < frames {
+            let mut server = server.lock().unwrap();
+            if !server.step_animation() {
+                let (width, height) = (server.config.width as f64, server.config.height as f64);
+                server.zoom_at(EXPORT_ZOOM_STEP, width / 2.0, height / 2.0);
+            }
+        }
+    }
+
+    info!("Exported {} frame(s) to {}", frames, directory.display());
+    Ok(paths)
+}
+
+/// Computes every tile of the current view in this process, without any worker.
+pub fn render_locally(server: &mut Server) -> RgbImage {
+    let (width, height, view) = (server.config.width, server.config.height, server.range);
+    let mut image = RgbImage::new(width, height);
+
+    while let Some(task) = server.next_task() {
+        let (result, data) = match task.perform() {
+            Ok(performed) => performed,
+            Err(e) => {
+                warn!("Skipping a tile that failed to compute: {}", e);
+                continue;
+            }
+        };
+
+        let iterations = task.intensity_format.counts(&data);
+        let rendering_data =
+            RenderingData::from_result(result, "local".to_string(), iterations, &server.palette);
+        draw_fragment(&mut image, &rendering_data, &view);
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::sync::mpsc::{self, Sender};
+
+    use crate::{
+        models::{
+            fragments::fragment_result::FragmentResult, pixel::pixel_data::PixelData, point::Point,
+            resolution::Resolution, u8_data::U8Data,
+        },
+        networking::server::ServerConfig,
+        rendering::color::PaletteHandler,
+    };
+
+    use super::*;
+
+    // stands in for the workers: computes the tasks of the current view as they come
+    async fn compute_locally(server: Arc<Mutex<Server>>, render_tx: Sender<RenderingData>) {
+        let worker: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        loop {
+            let task = {
+                let mut server = server.lock().unwrap();
+                let task = server.next_task();
+                if let Some(task) = &task {
+                    server.mark_task_inflight(worker, task.clone());
+                }
+                task
+            };
+            let Some(task) = task else {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                continue;
+            };
+
+            let (result, _) = task.perform().unwrap();
+            let rendering_data = {
+                let mut server = server.lock().unwrap();
+                if server.complete_task(&result).is_none() {
+                    continue;
+                }
+                let pixels = result.pixels.count as usize;
+                RenderingData::from_result(
+                    result,
+                    "local".to_string(),
+                    vec![0.0; pixels],
+                    &server.palette,
+                )
+            };
+            render_tx.send(rendering_data).await.unwrap();
+        }
+    }
+
+    fn fragment(range: Range, resolution: Resolution, color: (u8, u8, u8)) -> RenderingData {
+        let count = resolution.nx as u32 * resolution.ny as u32;
+        let result = FragmentResult::new(
+            U8Data::new(0, 16),
+            resolution,
+            range,
+            PixelData::new(0, count),
+        );
+        RenderingData {
+            result,
+            worker: "worker".to_string(),
+            pixels: vec![color; count as usize],
+            iterations: vec![0.0; count as usize],
+        }
+    }
+
+    #[test]
+    fn fragments_are_assembled_at_their_place_in_the_view() {
+        let view = Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0));
+        let mut image = RgbImage::new(4, 2);
+
+        let left = fragment(
+            Range::new(Point::new(-1.0, -1.0), Point::new(0.0, 1.0)),
+            Resolution::new(2, 2),
+            (255, 0, 0),
+        );
+        let right = fragment(
+            Range::new(Point::new(0.0, -1.0), Point::new(1.0, 1.0)),
+            Resolution::new(2, 2),
+            (0, 0, 255),
+        );
+        draw_fragment(&mut image, &left, &view);
+        draw_fragment(&mut image, &right, &view);
+
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([255, 0, 0]));
+        assert_eq!(image.get_pixel(1, 1), &Rgb([255, 0, 0]));
+        assert_eq!(image.get_pixel(2, 0), &Rgb([0, 0, 255]));
+        assert_eq!(image.get_pixel(3, 1), &Rgb([0, 0, 255]));
+    }
+
+    #[test]
+    fn pixels_outside_the_image_are_skipped() {
+        let view = Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0));
+        let mut image = RgbImage::new(2, 2);
+
+        let overflowing = fragment(
+            Range::new(Point::new(0.0, 0.0), Point::new(2.0, 2.0)),
+            Resolution::new(2, 2),
+            (0, 255, 0),
+        );
+        draw_fragment(&mut image, &overflowing, &view);
+
+        assert_eq!(image.get_pixel(1, 1), &Rgb([0, 255, 0]));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn stale_fragments_outside_the_view_are_not_drawn() {
+        let view = Range::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        let mut image = RgbImage::new(2, 2);
+
+        for range in [
+            Range::new(Point::new(-1.0, 0.0), Point::new(0.0, 1.0)),
+            Range::new(Point::new(0.0, -1.0), Point::new(1.0, 0.0)),
+            Range::new(Point::new(1e12, 0.0), Point::new(2e12, 1.0)),
+        ] {
+            draw_fragment(
+                &mut image,
+                &fragment(range, Resolution::new(2, 2), (0, 255, 0)),
+                &view,
+            );
+        }
+
+        assert!(image.pixels().all(|pixel| *pixel == Rgb([0, 0, 0])));
+    }
+
+    #[tokio::test]
+    async fn every_exported_frame_is_written_at_the_canvas_size() {
+        let (render_tx, mut render_rx) = mpsc::channel::<RenderingData>(32);
+        let config = ServerConfig::new("localhost".to_string(), 8787, 30, 20, 2);
+        let server = Arc::new(Mutex::new(Server::new(config, render_tx.clone())));
+        let local_worker = tokio::spawn(compute_locally(server.clone(), render_tx));
+        let directory = std::env::temp_dir().join(format!("frakt-frames-{}", std::process::id()));
+
+        let exported = export_frames(server, &mut render_rx, &directory, 3).await;
+        local_worker.abort();
+        let exported = exported.unwrap();
+
+        assert_eq!(exported.len(), 3);
+        for path in &exported {
+            assert_eq!(image::image_dimensions(path).unwrap(), (30, 20));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn a_local_mandelbrot_render_shows_its_interior() {
+        let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(1);
+        let config = ServerConfig::new("localhost".to_string(), 8787, 64, 64, 4);
+        let mut server = Server::new(config, render_tx);
+        server.set_fractal_by_name("mandelbrot").unwrap();
+
+        let image = render_locally(&mut server);
+
+        // (-0.5, 0) is the middle of the Mandelbrot framing and never escapes
+        let (r, g, b) = server.palette.calculate_color(1.0);
+        let interior = Rgb([r, g, b]);
+        assert_eq!(image.dimensions(), (64, 64));
+        assert_eq!(image.get_pixel(32, 32), &interior);
+        assert_ne!(image.get_pixel(0, 0), &interior);
+    }
+
+    #[test]
+    fn renders_of_the_same_config_are_identical() {
+        let render = || {
+            let (render_tx, _render_rx) = mpsc::channel::<RenderingData>(1);
+            let config = ServerConfig::new("localhost".to_string(), 8787, 48, 32, 4);
+            let mut server = Server::new(config, render_tx);
+            server.palette = PaletteHandler::with_seed(7);
+            render_locally(&mut server).into_raw()
+        };
+
+        // tiles are handed out in a random order, which must not show in the result
+        assert_eq!(render(), render());
+    }
+}
@@ -2,15 +2,20 @@
 #![forbid(unsafe_code)]
 
 pub mod color;
+pub mod error;
+pub mod headless;
+pub mod render_queue;
 
-use log::info;
-use pixels::{Error, Pixels, SurfaceTexture};
+use log::{error, info, trace};
+use pixels::{Pixels, SurfaceTexture};
 
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc::Receiver;
+use tokio::{runtime::Handle, sync::mpsc::Receiver, sync::oneshot};
+use tokio_util::sync::CancellationToken;
 use winit::dpi::LogicalSize;
 use winit::event::{Event, VirtualKeyCode};
-use winit::event_loop::{ControlFlow, EventLoop};
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder};
+use winit::platform::run_return::EventLoopExtRunReturn;
 use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
@@ -19,69 +24,112 @@ use crate::dtos::rendering_data::RenderingData;
 use crate::models::range::Range;
 use crate::networking::server::Server;
 
-use self::color::PaletteHandler;
-
-type SharedRenderingData = Arc<Vec<Mutex<Option<RenderingData>>>>;
+use self::color::Rgb;
+use self::error::GraphicsError;
+use self::render_queue::RenderQueue;
 
 struct World {
     server: Arc<Mutex<Server>>,
     width: u32,
     height: u32,
-    rendering_data_shards: SharedRenderingData,
-    palette: PaletteHandler,
+    render_queue: Arc<RenderQueue>,
 }
 
-fn initialize_shared_data(shard_count: usize) -> SharedRenderingData {
-    let shards = (0..shard_count).map(|_| Mutex::new(None)).collect();
-    Arc::new(shards)
+/// Opens the window and draws the fragments received until it is closed, or until `closed` is
+/// cancelled. Fails without drawing anything when no window can be opened, e.g. on a machine
+/// without a display, or on macOS where windows belong to the main thread.
+///
+/// The event loop blocks, it runs on a thread of its own rather than on one of the runtime.
+pub async fn launch_graphics_engine(
+    server: Arc<Mutex<Server>>,
+    rendering_data_receiver: Receiver<RenderingData>,
+    closed: CancellationToken,
+) -> Result<(), GraphicsError> {
+    let runtime = Handle::current();
+    on_graphics_thread(move || {
+        run_graphics_engine(runtime, server, rendering_data_receiver, closed)
+    })
+    .await
 }
 
-pub async fn launch_graphics_engine(
+/// Runs `graphics` on a dedicated thread, resolving with its result once it returns.
+pub async fn on_graphics_thread<F>(graphics: F) -> Result<(), GraphicsError>
+where
+    F: FnOnce() -> Result<(), GraphicsError> + Send + 'static,
+{
+    let (done_tx, done_rx) = oneshot::channel();
+    std::thread::Builder::new()
+        .name("graphics".to_string())
+        .spawn(move || {
+            let _ = done_tx.send(graphics());
+        })?;
+
+    done_rx.await.unwrap_or_else(|_| {
+        Err(GraphicsError::EventLoop(
+            "the graphics thread panicked".to_string(),
+        ))
+    })
+}
+
+fn build_event_loop() -> EventLoop<()> {
+    let mut builder = EventLoopBuilder::new();
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(&mut builder, true);
+    #[cfg(target_os = "windows")]
+    winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(&mut builder, true);
+    builder.build()
+}
+
+fn run_graphics_engine(
+    runtime: Handle,
     server: Arc<Mutex<Server>>,
     mut rendering_data_receiver: Receiver<RenderingData>,
-) -> Result<(), Error> {
-    let event_loop = EventLoop::new();
+    closed: CancellationToken,
+) -> Result<(), GraphicsError> {
+    // winit panics rather than report a missing display, the panic is turned into the error
+    // reported instead of being printed
+    let panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let event_loop = std::panic::catch_unwind(build_event_loop);
+    std::panic::set_hook(panic_hook);
+    let mut event_loop = event_loop.map_err(|panic| {
+        let reason = panic
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| panic.downcast_ref::<&str>().copied())
+            .unwrap_or("no display available");
+        GraphicsError::EventLoop(reason.to_string())
+    })?;
     let mut input_helper = WinitInputHelper::new();
 
-    let (width, height) = {
+    let (width, height, queue_capacity) = {
         let server = server.lock().unwrap();
         let width = server.config.width;
         let height = server.config.height;
-        (width, height)
+        (width, height, server.config.render_queue_capacity())
     };
 
-    let rendering_data = initialize_shared_data(10);
+    let render_queue = Arc::new(RenderQueue::new(queue_capacity));
     let mut graphics_world = World {
         server,
         width,
         height,
-        rendering_data_shards: rendering_data.clone(),
-        palette: PaletteHandler::new(),
+        render_queue: render_queue.clone(),
     };
 
-    tokio::spawn(async move {
-        loop {
-            while let Some(data) = rendering_data_receiver.recv().await {
-                for shard in rendering_data.iter() {
-                    if let Ok(mut shard_lock) = shard.lock() {
-                        if shard_lock.is_none() {
-                            *shard_lock = Some(data.clone());
-                            break;
-                        }
-                    }
-                }
-            }
-        }
-    });
-
     let window = {
         let size = LogicalSize::new(graphics_world.width as f64, graphics_world.height as f64);
         WindowBuilder::new()
             .with_title("Frakt")
             .with_inner_size(size)
             .with_min_inner_size(size)
-            .build(&event_loop)
-            .expect("Failed to create window")
+            .build(&event_loop)?
     };
 
     let mut pixels = {
@@ -90,7 +138,22 @@ pub async fn launch_graphics_engine(
         Pixels::new(graphics_world.width, graphics_world.height, surface_texture)?
     };
 
-    event_loop.run(move |event, _, control_flow| {
+    runtime.spawn(async move {
+        while let Some(data) = rendering_data_receiver.recv().await {
+            render_queue.push(data).await;
+        }
+    });
+
+    event_loop.run_return(|event, _, control_flow| {
+        if closed.is_cancelled() {
+            *control_flow = ControlFlow::Exit;
+        }
+
+        if let Event::LoopDestroyed = event {
+            graphics_world.save_state();
+            return;
+        }
+
         if let Event::RedrawRequested(_) = event {
             graphics_world.render(pixels.frame_mut());
             if pixels.render().is_err() {
@@ -120,6 +183,36 @@ pub async fn launch_graphics_engine(
                 graphics_world.server.lock().unwrap().move_up();
             }
 
+            // dragging with the left button moves the fractal along with the cursor
+            if input_helper.mouse_held(0) {
+                let (dx, dy) = input_helper.mouse_diff();
+                if dx != 0.0 || dy != 0.0 {
+                    let window_size = window.inner_size();
+                    let scale_x = graphics_world.width as f64 / window_size.width as f64;
+                    let scale_y = graphics_world.height as f64 / window_size.height as f64;
+                    graphics_world
+                        .server
+                        .lock()
+                        .unwrap()
+                        .pan(-dx as f64 * scale_x, -dy as f64 * scale_y);
+                }
+            }
+
+            // scrolling zooms around the point under the cursor, one notch being a 10% step
+            let scroll = input_helper.scroll_diff();
+            if scroll != 0.0 {
+                if let Some(Ok((x, y))) = input_helper
+                    .mouse()
+                    .map(|position| pixels.window_pos_to_pixel(position))
+                {
+                    graphics_world.server.lock().unwrap().zoom_at(
+                        0.9f64.powf(scroll as f64),
+                        x as f64,
+                        y as f64,
+                    );
+                }
+            }
+
             if input_helper.key_pressed(VirtualKeyCode::P) {
                 graphics_world.server.lock().unwrap().zoom(0.9); // Zoom in
             }
@@ -127,8 +220,41 @@ pub async fn launch_graphics_engine(
                 graphics_world.server.lock().unwrap().zoom(1.1); // Zoom out
             }
 
+            // doubling or halving the iterations brings out or hides the details of deep zooms
+            if input_helper.key_pressed(VirtualKeyCode::Plus)
+                || input_helper.key_pressed(VirtualKeyCode::NumpadAdd)
+            {
+                let mut server = graphics_world.server.lock().unwrap();
+                let max_iteration = server.max_iteration.saturating_mul(2);
+                server.set_max_iteration(max_iteration);
+                info!("Rendering with {} iterations", server.max_iteration);
+            }
+            if input_helper.key_pressed(VirtualKeyCode::Minus)
+                || input_helper.key_pressed(VirtualKeyCode::NumpadSubtract)
+            {
+                let mut server = graphics_world.server.lock().unwrap();
+                let max_iteration = server.max_iteration / 2;
+                server.set_max_iteration(max_iteration);
+                info!("Rendering with {} iterations", server.max_iteration);
+            }
+
+            if input_helper.key_pressed(VirtualKeyCode::Space) {
+                let mut server = graphics_world.server.lock().unwrap();
+                let paused = !server.paused;
+                server.set_paused(paused);
+                info!(
+                    "Task dispatch {}",
+                    if paused { "paused" } else { "resumed" }
+                );
+            }
+
             if input_helper.key_pressed(VirtualKeyCode::K) {
-                graphics_world.server.lock().unwrap().cycle_fractal();
+                let mut server = graphics_world.server.lock().unwrap();
+                if input_helper.held_shift() {
+                    server.cycle_fractal_prev();
+                } else {
+                    server.cycle_fractal();
+                }
             }
 
             if input_helper.key_pressed(VirtualKeyCode::L) {
@@ -151,78 +277,178 @@ pub async fn launch_graphics_engine(
             window.request_redraw();
         }
     });
+
+    info!("Graphics closed");
+    Ok(())
+}
+
+// the iterations of a fragment are stored row by row, so a row is `nx` pixels wide
+fn fragment_pixel_index(x: u16, y: u16, nx: u16) -> usize {
+    x as usize + y as usize * nx as usize
+}
+
+// calculate the start point of the fragment, given the resolution and range and the server's range
+// the server range is the current view of the fractal, it is dynamic and changes as the user moves and zooms
+// we need to calculate the start point of the fragment in the canvas, given the resolution and the range of the fragment
+// a fragment left over from a previous view may start outside of the canvas, on either side
+fn fragment_origin(range: &Range, view: &Range, width: u32, height: u32) -> (i64, i64) {
+    let Range {
+        min: view_min,
+        max: view_max,
+    } = view;
+    // tile edges sit on whole pixels, rounding absorbs the error of the round trip through f64
+    let x = ((range.min.x - view_min.x) / (view_max.x - view_min.x) * width as f64).round() as i64;
+    let y = ((range.min.y - view_min.y) / (view_max.y - view_min.y) * height as f64).round() as i64;
+
+    (x, y)
 }
 
 impl World {
     fn update(&mut self) {}
 
+    fn save_state(&self) {
+        let server = self.server.lock().unwrap();
+        if let Some(path) = &server.config.state_file {
+            match server.save_state(path) {
+                Ok(()) => info!("Saved the view state to {}", path.display()),
+                Err(e) => error!("Failed to save the view state: {}", e),
+            }
+        }
+    }
+
     fn cycle_color_palette_forward(&mut self) {
-        self.palette.cycle_palette_forward();
-        self.server.lock().unwrap().regenerate_tiles();
+        let mut server = self.server.lock().unwrap();
+        server.palette.cycle_palette_forward();
+        server.invalidate_and_retile();
     }
 
     fn cycle_color_palette_backward(&mut self) {
-        self.palette.cycle_palette_backward();
-        self.server.lock().unwrap().regenerate_tiles();
+        let mut server = self.server.lock().unwrap();
+        server.palette.cycle_palette_backward();
+        server.invalidate_and_retile();
     }
 
     fn render(&self, frame_buffer: &mut [u8]) {
-        for shard in self.rendering_data_shards.iter() {
-            if let Ok(mut data_lock) = shard.lock() {
-                if let Some(render_data) = data_lock.take() {
-                    // Safely take the value, replacing it with None
-                    info!("Rendering result: {:?}", render_data.result);
-                    let result = render_data.result;
-
-                    let (start_x, start_y) = self.start_point(result.range);
-                    info!("Drawing fragment at ({}, {})", start_x, start_y);
-
-                    for y in 0..result.resolution.ny {
-                        for x in 0..result.resolution.nx {
-                            let t = render_data.iterations[(x + y * result.resolution.ny) as usize];
-                            self.draw_pixel(
-                                frame_buffer,
-                                self.width,
-                                start_x + x as u32,
-                                start_y + y as u32,
-                                t,
-                            );
-                        }
-                    }
+        while let Some(render_data) = self.render_queue.pop() {
+            info!("Rendering result: {:?}", render_data.result);
+            let result = render_data.result;
+
+            let (start_x, start_y) = self.start_point(result.range);
+            info!("Drawing fragment at ({}, {})", start_x, start_y);
+
+            for y in 0..result.resolution.ny {
+                for x in 0..result.resolution.nx {
+                    let color =
+                        render_data.pixels[fragment_pixel_index(x, y, result.resolution.nx)];
+                    draw_pixel(
+                        frame_buffer,
+                        self.width,
+                        start_x + x as i64,
+                        start_y + y as i64,
+                        color,
+                    );
                 }
             }
         }
     }
 
-    // calculate the start point of the fragment, given the resolution and range and the server's range
-    // the server range is the current view of the fractal, it is dynamic and changes as the user moves and zooms
-    // we need to calculate the start point of the fragment in the canvas, given the resolution and the range of the fragment
-    fn start_point(&self, range: Range) -> (u32, u32) {
-        let server = self.server.lock().unwrap();
-        let Range {
-            min: server_min,
-            max: server_max,
-        } = server.range;
-        let x = ((range.min.x - server_min.x) / (server_max.x - server_min.x) * self.width as f64)
-            as u32;
-        let y = ((range.min.y - server_min.y) / (server_max.y - server_min.y) * self.height as f64)
-            as u32;
-
-        (x, y)
+    fn start_point(&self, range: Range) -> (i64, i64) {
+        let view = self.server.lock().unwrap().range;
+        fragment_origin(&range, &view, self.width, self.height)
+    }
+}
+
+// returns whether the pixel was drawn, pixels falling outside of the canvas are skipped
+fn draw_pixel(frame_buffer: &mut [u8], width: u32, x: i64, y: i64, color: Rgb) -> bool {
+    let height = frame_buffer.len() / 4 / (width as usize).max(1);
+    if !(0..width as i64).contains(&x) || !(0..height as i64).contains(&y) {
+        trace!(
+            "Skipping pixel ({}, {}) outside the bounds of the frame buffer",
+            x,
+            y
+        );
+        return false;
+    }
+
+    let index = (y as usize * width as usize + x as usize) * 4;
+    let (r, g, b) = color;
+
+    frame_buffer[index] = r;
+    frame_buffer[index + 1] = g;
+    frame_buffer[index + 2] = b;
+    frame_buffer[index + 3] = 0xff;
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{point::Point, tile::Tile};
+
+    use super::*;
+
+    #[test]
+    fn fragment_pixel_index_uses_row_width() {
+        let (nx, ny) = (200u16, 100u16);
+
+        assert_eq!(fragment_pixel_index(0, 0, nx), 0);
+        assert_eq!(fragment_pixel_index(199, 0, nx), 199);
+        assert_eq!(fragment_pixel_index(0, 1, nx), 200);
+        assert_eq!(fragment_pixel_index(nx - 1, ny - 1, nx), 200 * 100 - 1);
+    }
+
+    #[test]
+    fn tiles_of_a_wide_canvas_start_at_their_own_pixel() {
+        let (width, height, tiles) = (400, 200, 4);
+        let view = Range::new(Point::new(-2.1, -1.3), Point::new(0.7, 1.3));
+
+        for index in 0..tiles * tiles {
+            let tile = Tile::from_grid(index, tiles, width, height, &view);
+            let expected = (
+                (index % tiles * width / tiles) as i64,
+                (index / tiles * height / tiles) as i64,
+            );
+
+            assert_eq!(fragment_origin(&tile.range, &view, width, height), expected);
+        }
+    }
+
+    #[test]
+    fn axes_are_scaled_independently() {
+        let view = Range::new(Point::new(0.0, 0.0), Point::new(4.0, 1.0));
+        let range = Range::new(Point::new(2.0, 0.5), Point::new(3.0, 1.0));
+
+        assert_eq!(fragment_origin(&range, &view, 400, 200), (200, 100));
     }
 
-    fn draw_pixel(&self, frame_buffer: &mut [u8], width: u32, x: u32, y: u32, t: f64) {
-        let index = ((y * width + x) * 4) as usize;
+    #[test]
+    fn fragments_outside_of_the_view_start_outside_of_the_canvas() {
+        let view = Range::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        let above_left = Range::new(Point::new(-1.0, -0.5), Point::new(0.0, 0.0));
+        let far_right = Range::new(Point::new(1e12, 0.0), Point::new(2e12, 1.0));
 
-        if index + 3 < frame_buffer.len() {
-            let (r, g, b) = self.palette.calculate_color(t);
+        assert_eq!(fragment_origin(&above_left, &view, 100, 100), (-100, -50));
+        assert!(fragment_origin(&far_right, &view, 100, 100).0 > u32::MAX as i64);
+    }
 
-            frame_buffer[index] = r;
-            frame_buffer[index + 1] = g;
-            frame_buffer[index + 2] = b;
-            frame_buffer[index + 3] = 0xff;
-        } else {
-            panic!("Attempting to draw pixel outside the bounds of the frame buffer");
+    #[test]
+    fn pixels_outside_of_the_frame_buffer_are_skipped() {
+        let (width, height) = (4u32, 3u32);
+        let mut frame_buffer = vec![0u8; (width * height * 4) as usize];
+
+        for (x, y) in [
+            (-1, 0),
+            (0, -1),
+            (width as i64, 0),
+            (0, height as i64),
+            (i64::MAX, i64::MAX),
+            (u32::MAX as i64 + 1, 0),
+        ] {
+            assert!(!draw_pixel(&mut frame_buffer, width, x, y, (1, 2, 3)));
         }
+        assert!(frame_buffer.iter().all(|&byte| byte == 0));
+
+        assert!(draw_pixel(&mut frame_buffer, width, 3, 2, (1, 2, 3)));
+        assert_eq!(&frame_buffer[frame_buffer.len() - 4..], [1, 2, 3, 0xff]);
     }
 }
@@ -0,0 +1,103 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use tokio::sync::Semaphore;
+
+use crate::dtos::rendering_data::RenderingData;
+
+/// Bounded hand-off between the connections and the window: producers wait for a free slot
+/// instead of dropping fragments, the event loop drains it without blocking.
+pub struct RenderQueue {
+    pending: Mutex<VecDeque<RenderingData>>,
+    free_slots: Semaphore,
+    capacity: usize,
+}
+
+impl RenderQueue {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            pending: Mutex::new(VecDeque::with_capacity(capacity)),
+            free_slots: Semaphore::new(capacity),
+            capacity,
+        }
+    }
+
+    /// Queues `data`, waiting for the window to draw some fragments if the queue is full.
+    pub async fn push(&self, data: RenderingData) {
+        // the semaphore is never closed, the slot is given back by `pop`
+        if let Ok(slot) = self.free_slots.acquire().await {
+            slot.forget();
+        }
+        self.pending.lock().unwrap().push_back(data);
+    }
+
+    pub fn pop(&self) -> Option<RenderingData> {
+        let data = self.pending.lock().unwrap().pop_front()?;
+        self.free_slots.add_permits(1);
+        Some(data)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::models::{
+        fragments::fragment_result::FragmentResult, pixel::pixel_data::PixelData, point::Point,
+        range::Range, resolution::Resolution, u8_data::U8Data,
+    };
+
+    use super::*;
+
+    fn fragment(index: u32) -> RenderingData {
+        RenderingData {
+            result: FragmentResult::new(
+                U8Data::new(index, 16),
+                Resolution::new(1, 1),
+                Range::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0)),
+                PixelData::new(16, 1),
+            ),
+            worker: "worker".to_string(),
+            pixels: vec![(0, 0, 0)],
+            iterations: vec![0.0],
+        }
+    }
+
+    #[tokio::test]
+    async fn fragments_beyond_the_capacity_wait_instead_of_being_lost() {
+        let queue = Arc::new(RenderQueue::new(2));
+        let writer = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                for index in 0..10 {
+                    queue.push(fragment(index)).await;
+                }
+            }
+        });
+
+        let mut received = Vec::new();
+        while received.len() < 10 {
+            assert!(queue.len() <= queue.capacity());
+            match queue.pop() {
+                Some(data) => received.push(data.result.id.offset),
+                None => tokio::task::yield_now().await,
+            }
+        }
+        writer.await.unwrap();
+
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+        assert!(queue.is_empty());
+    }
+}
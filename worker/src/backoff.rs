@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+/// Exponential backoff between reconnection attempts.
+#[derive(Debug)]
+pub struct Backoff {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_retries: u32,
+    retries: u32,
+}
+
+impl Backoff {
+    pub fn new(initial_delay: Duration, max_delay: Duration, max_retries: u32) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            max_retries,
+            retries: 0,
+        }
+    }
+
+    /// Delay to wait before the next attempt, `None` once the retries are exhausted.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.retries >= self.max_retries {
+            return None;
+        }
+
+        let delay = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(self.retries))
+            .min(self.max_delay);
+        self.retries += 1;
+        Some(delay)
+    }
+
+    pub fn reset(&mut self) {
+        self.retries = 0;
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_double_up_to_the_ceiling_until_the_retries_run_out() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500), 5);
+
+        let delays: Vec<_> = std::iter::from_fn(|| backoff.next_delay()).collect();
+
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+        assert_eq!(backoff.retries(), 5);
+        assert_eq!(backoff.next_delay(), None);
+    }
+
+    #[test]
+    fn a_reset_starts_over_from_the_initial_delay() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1), 3);
+        backoff.next_delay();
+        backoff.next_delay();
+
+        backoff.reset();
+
+        assert_eq!(backoff.retries(), 0);
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+    }
+}
@@ -1,48 +1,123 @@
-use std::time::Duration;
+mod backoff;
+mod result_queue;
+mod tile_cache;
+
+use std::{io::ErrorKind, time::Duration};
 
 use image::EncodableLayout;
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 use serde_json;
 use shared::{
-    models::fragments::{
-        fragment::Fragment, fragment_request::FragmentRequest, fragment_result::FragmentResult,
-        fragment_task::FragmentTask,
+    env,
+    models::{
+        fragments::{
+            error_message::ErrorMessage, fragment::Fragment, fragment_request::FragmentRequest,
+            fragment_result::FragmentResult, fragment_task::FragmentTask, heartbeat::Heartbeat,
+        },
+        pixel::pixel_intensity::IntensityFormat,
     },
     networking::{
-        read_binary_data, read_json_message, read_message_length, result::NetworkingResult,
-        send_message, send_result, worker::Worker,
+        compression::Compression, error::NetworkingError, read_message_raw_with_limit,
+        result::NetworkingResult, send_message, send_result, signature::verify_payload, tls,
+        worker::Worker, RawMessage, DEFAULT_MAX_MESSAGE_SIZE,
     },
 };
-use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+use self::{
+    backoff::Backoff,
+    result_queue::{PendingResult, ResultQueue},
+    tile_cache::TileCache,
+};
+
+// TODO: put the pending results capacity in a config file
+const MAX_PENDING_RESULTS: usize = 16;
+
+/// How a connection to the server ended, short of an error.
+enum Disconnect {
+    Shutdown,
+    /// The server closed the connection instead of handing out a task, after `tasks` were
+    /// delivered on it.
+    NoTask {
+        tasks: usize,
+    },
+}
 
-pub async fn run_worker(worker: Worker) {
+/// Runs `worker` until it gives up or `shutdown` is cancelled, in which case the
+/// task in progress is completed and its result delivered before returning.
+pub async fn run_worker(mut worker: Worker, shutdown: CancellationToken) {
     info!("Starting worker: {}", worker.name);
-    let mut retries: usize = 0;
-    let max_retries: usize = 10;
+    if env::secret().is_empty() {
+        warn!(
+            "{} is not set, the messages are signed with an empty key anyone can forge",
+            env::SECRET_ENV_VAR
+        );
+    }
     let handle = tokio::spawn(async move {
+        let mut pending_results = ResultQueue::new(MAX_PENDING_RESULTS);
+        let mut tile_cache = TileCache::new(worker.tile_cache_capacity);
+        let mut backoff = Backoff::new(
+            worker.retry_delay,
+            worker.max_retry_delay,
+            worker.max_retries,
+        );
+        // a server without tasks, paused or done with its frame, is polled for as long as it takes
+        let mut idle = Backoff::new(worker.retry_delay, worker.max_retry_delay, u32::MAX);
         loop {
-            match run(&worker).await {
-                Ok(_) => {
-                    retries = 0;
-                    info!("Worker task completed.")
+            let delay = match run(
+                &mut worker,
+                &mut pending_results,
+                &mut tile_cache,
+                &mut backoff,
+                &shutdown,
+            )
+            .await
+            {
+                Ok(Disconnect::Shutdown) => {
+                    info!("Worker {} shut down.", worker.name);
+                    break;
                 }
-                Err(e) => {
-                    retries += 1;
-                    // TODO: Implement a more robust error handling mechanism
-                    // TODO: put the max_retries in a config file
-                    // if retries >= max_retries {
-                    //     error!("Worker killed due to multiple errors encountered in a row");
-                    //     // break;
-                    // }
-                    error!(
-                        "Worker encountered an error: {}, retry {}/{}",
-                        e, retries, max_retries
-                    )
+                Ok(Disconnect::NoTask { tasks }) => {
+                    if tasks > 0 {
+                        idle.reset();
+                    }
+                    let delay = idle.next_delay().unwrap_or(worker.max_retry_delay);
+                    debug!(
+                        "The server has no task to hand out, polling again in {:?}",
+                        delay
+                    );
+                    delay
                 }
-            }
+                Err(e) => match backoff.next_delay() {
+                    Some(delay) => {
+                        error!(
+                            "Worker encountered an error: {}, retry {}/{} in {:?}",
+                            e,
+                            backoff.retries(),
+                            backoff.max_retries(),
+                            delay
+                        );
+                        delay
+                    }
+                    None => {
+                        error!(
+                            "Worker killed due to {} errors encountered in a row, last one: {}",
+                            backoff.max_retries(),
+                            e
+                        );
+                        break;
+                    }
+                },
+            };
 
-            // Add a short delay to prevent a tight loop in case of repeated errors
-            tokio::time::sleep(Duration::from_millis(100)).await;
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.cancelled() => {
+                    info!("Worker {} shut down while waiting to retry.", worker.name);
+                    break;
+                }
+            }
         }
     });
 
@@ -51,92 +126,584 @@ pub async fn run_worker(worker: Worker) {
     }
 }
 
-async fn run(worker: &Worker) -> NetworkingResult<()> {
-    let server_addr = format!("{}:{}", worker.address, worker.port);
-    debug!("Connecting to server at {}", server_addr);
-    let mut stream = connect_to_server(&server_addr).await?;
+async fn run(
+    worker: &mut Worker,
+    pending_results: &mut ResultQueue,
+    tile_cache: &mut TileCache,
+    backoff: &mut Backoff,
+    shutdown: &CancellationToken,
+) -> NetworkingResult<Disconnect> {
+    let transport = worker.transport();
+    debug!("Connecting to server at {}", transport);
+    let stream = transport.connect().await.map_err(|e| {
+        error!("Failed to connect to server at {}: {}", transport, e);
+        e
+    })?;
 
-    loop {
+    if !worker.tls {
+        return work(
+            stream,
+            worker,
+            pending_results,
+            tile_cache,
+            backoff,
+            shutdown,
+        )
+        .await;
+    }
+    let connector = tls::connector(worker.tls_ca.as_deref(), worker.tls_insecure)?;
+    let server_name = tls::server_name(&worker.address)?;
+    let stream = connector.connect(server_name, stream).await.map_err(|e| {
+        error!("TLS handshake with {} failed: {}", transport, e);
+        NetworkingError::from(e)
+    })?;
+    work(
+        stream,
+        worker,
+        pending_results,
+        tile_cache,
+        backoff,
+        shutdown,
+    )
+    .await
+}
+
+// The connection is kept open for as long as the server hands out tasks
+async fn work<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    worker: &mut Worker,
+    pending_results: &mut ResultQueue,
+    tile_cache: &mut TileCache,
+    backoff: &mut Backoff,
+    shutdown: &CancellationToken,
+) -> NetworkingResult<Disconnect> {
+    deliver_pending_results(&mut stream, pending_results, worker.compression).await?;
+
+    let mut tasks = 0;
+    while !shutdown.is_cancelled() {
         debug!("Sending fragment request");
         send_fragment_request(&mut stream, worker).await?;
+        // the server is reachable, only errors in a row wear the worker out
+        backoff.reset();
 
-        let (signature, task) = read_fragment_task(&mut stream).await?;
+        // Once requested, a task is seen through even if a shutdown comes in meanwhile
+        let Some(task) = read_fragment_task(
+            &mut stream,
+            worker.read_timeout,
+            worker.max_iteration_ceiling,
+        )
+        .await?
+        else {
+            return Ok(Disconnect::NoTask { tasks });
+        };
+        if let Some(name) = task
+            .assigned_name
+            .as_ref()
+            .filter(|name| **name != worker.name)
+        {
+            info!("Worker {} was registered as {}", worker.name, name);
+            worker.name = name.clone();
+        }
 
-        debug!("Performing task");
-        let (result, data) = perform_task(&task)?;
+        let (result, data) = match tile_cache.get(&task) {
+            Some(cached) => {
+                debug!(
+                    "Serving the task from the tile cache, {} hits among {} tiles",
+                    tile_cache.hits(),
+                    tile_cache.len()
+                );
+                cached
+            }
+            None => {
+                debug!("Performing task");
+                let (result, data) = perform_task(&mut stream, worker, task.clone()).await?;
+                tile_cache.insert(&task, result.clone(), data.clone());
+                (result, data)
+            }
+        };
 
         debug!("Sending fragment result");
-        let mut stream = connect_to_server(&server_addr).await?;
-        send_fragment_result(&result, &mut stream, &data, &signature).await?;
+        let pending = PendingResult::new(result, data);
+        if let Err(e) = send_fragment_result(
+            &pending.result,
+            &mut stream,
+            &pending.data,
+            worker.compression,
+        )
+        .await
+        {
+            warn!("Queueing undelivered FragmentResult for a later retry");
+            if pending_results.push(pending).is_some() {
+                warn!("Pending results queue is full, dropped the oldest result");
+            }
+            return Err(e);
+        }
+        tasks += 1;
 
-        _ = stream.shutdown().await?;
+        if !worker.task_delay.is_zero() {
+            debug!("Waiting {:?} before the next task", worker.task_delay);
+            tokio::select! {
+                _ = tokio::time::sleep(worker.task_delay) => {}
+                _ = shutdown.cancelled() => {}
+            }
+        }
     }
+
+    debug!("Closing the connection to {}", worker.transport());
+    stream.shutdown().await?;
+    Ok(Disconnect::Shutdown)
 }
 
-fn perform_task(task: &FragmentTask) -> NetworkingResult<(FragmentResult, Vec<u8>)> {
+async fn deliver_pending_results<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    pending_results: &mut ResultQueue,
+    compression: Compression,
+) -> NetworkingResult<()> {
+    if !pending_results.is_empty() {
+        info!(
+            "Delivering {} pending FragmentResult(s)",
+            pending_results.len()
+        );
+    }
+
+    while let Some(pending) = pending_results.pop() {
+        if let Err(e) =
+            send_fragment_result(&pending.result, stream, &pending.data, compression).await
+        {
+            if pending_results.requeue(pending).is_some() {
+                warn!("Pending results queue is full, dropped the oldest result");
+            }
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+// The task is computed on a blocking thread so heartbeats keep flowing while it runs
+async fn perform_task<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    worker: &Worker,
+    task: FragmentTask,
+) -> NetworkingResult<(FragmentResult, Vec<u8>)> {
     debug!("Performing FragmentTask: {:?}", task);
-    task.perform().map_err(|e| {
-        error!("Failed to perform the FragmentTask: {}", e);
-        e.into()
-    })
+    let mut computation = tokio::task::spawn_blocking(move || {
+        task.perform_timed()
+            .map(|(result, data, elapsed)| {
+                debug!("Computed the FragmentTask in {:?}", elapsed);
+                (result, data)
+            })
+            .map_err(|e| e.to_string())
+    });
+
+    let mut heartbeat = tokio::time::interval(worker.heartbeat_interval);
+    heartbeat.tick().await;
+    loop {
+        tokio::select! {
+            outcome = &mut computation => {
+                return match outcome {
+                    Ok(Ok(performed)) => Ok(performed),
+                    Ok(Err(e)) => {
+                        error!("Failed to perform the FragmentTask: {}", e);
+                        Err(NetworkingError::Error(e.into()))
+                    }
+                    Err(e) => {
+                        error!("FragmentTask computation panicked: {}", e);
+                        Err(NetworkingError::Error(Box::new(e)))
+                    }
+                };
+            }
+            _ = heartbeat.tick() => send_heartbeat(stream, worker).await?,
+        }
+    }
 }
 
-async fn send_fragment_result(
+async fn send_heartbeat<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    worker: &Worker,
+) -> NetworkingResult<()> {
+    let heartbeat = Heartbeat::new(worker.name.clone());
+    let serialized_heartbeat = serde_json::to_string(&heartbeat.to_json()?)?;
+    trace!("Sending Heartbeat: {}", serialized_heartbeat);
+
+    send_message(
+        stream,
+        serialized_heartbeat.as_bytes(),
+        None,
+        Compression::None,
+    )
+    .await
+}
+
+async fn send_fragment_result<S: AsyncWrite + Unpin>(
     result: &FragmentResult,
-    stream: &mut TcpStream,
+    stream: &mut S,
     data: &[u8],
-    signature: &[u8],
+    compression: Compression,
 ) -> NetworkingResult<()> {
-    debug!(
-        "Preparing to send FragmentResult with signature: {:?}",
-        signature
-    );
+    debug!("Preparing to send FragmentResult");
     let serialized_fragment_result = result.to_json()?;
     let fragment_result_json = serde_json::to_string(&serialized_fragment_result)?;
     trace!("Serialized FragmentResult: {}", serialized_fragment_result);
     trace!("Sending data: {:?}", data);
 
-    send_result(stream, &fragment_result_json, data, signature)
+    send_result(stream, &fragment_result_json, data, compression)
         .await
         .map_err(|e| {
             error!("Failed to send FragmentResult: {}", e);
-            e.into()
+            e
         })
 }
 
-async fn read_fragment_task(stream: &mut TcpStream) -> NetworkingResult<(Vec<u8>, FragmentTask)> {
+async fn read_fragment_task<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    read_timeout: Duration,
+    max_iteration_ceiling: u32,
+) -> NetworkingResult<Option<FragmentTask>> {
     debug!("Reading FragmentTask from stream");
-    let message_length = read_message_length(stream).await?;
-    let json_length = read_message_length(stream).await?;
-    let json_message = read_json_message(stream, json_length as usize).await?;
-    let data_message = read_binary_data(stream, (message_length - json_length) as usize).await?;
+    let RawMessage {
+        json_message, data, ..
+    } = match read_message_raw_with_limit(stream, DEFAULT_MAX_MESSAGE_SIZE, read_timeout).await {
+        Ok(message) => message,
+        // the server closes the connection when it has no task to hand out
+        Err(NetworkingError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
 
     trace!("Received JSON message: {}", json_message);
-    let task = FragmentTask::from_json(&json_message)?;
+    if let Ok(error) = ErrorMessage::from_json(&json_message) {
+        warn!("The server turned the request down: {}", error.message);
+        return Ok(None);
+    }
+    if !verify_payload(env::secret(), json_message.as_bytes(), &[], &data) {
+        error!("Rejecting FragmentTask with an invalid signature");
+        return Err(NetworkingError::SignatureMismatch);
+    }
+    let mut task = FragmentTask::from_json(&json_message)
+        .map_err(|e| NetworkingError::serde(e, &json_message))?;
+    let requested_iterations = task.max_iteration;
+    if task.clamp_max_iteration(max_iteration_ceiling) {
+        warn!(
+            "FragmentTask asks for {} iterations, lowered to {}",
+            requested_iterations, max_iteration_ceiling
+        );
+    }
 
     info!("Deserialized FragmentTask successfully");
     debug!("FragmentTask details: {:?}", task);
 
-    Ok((data_message, task))
+    Ok(Some(task))
+}
+
+// the codecs this worker inflates tasks with and the intensity formats it sends results in
+fn capabilities() -> Vec<String> {
+    let compressions = [Compression::Gzip, Compression::Zstd].map(|c| c.to_string());
+    let formats = [IntensityFormat::U16, IntensityFormat::F64].map(|f| f.to_string());
+    compressions.into_iter().chain(formats).collect()
 }
 
-async fn send_fragment_request(stream: &mut TcpStream, worker: &Worker) -> NetworkingResult<()> {
-    let request = FragmentRequest::new(worker.name.clone(), worker.maximal_work_load);
+async fn send_fragment_request<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    worker: &Worker,
+) -> NetworkingResult<()> {
+    let request = FragmentRequest::with_capabilities(
+        worker.name.clone(),
+        worker.maximal_work_load,
+        capabilities(),
+    );
     let serialized_request = request.to_json()?;
     let serialized_fragment_request = serde_json::to_string(&serialized_request)?;
     debug!("Sending FragmentRequest: {}", serialized_fragment_request);
 
-    send_message(stream, serialized_fragment_request.as_bytes(), None)
-        .await
-        .map_err(|e| {
-            error!("Failed to send FragmentRequest: {}", e);
-            e.into()
-        })
+    send_message(
+        stream,
+        serialized_fragment_request.as_bytes(),
+        None,
+        Compression::None,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to send FragmentRequest: {}", e);
+        e
+    })
 }
 
-async fn connect_to_server(addr: &str) -> NetworkingResult<TcpStream> {
-    TcpStream::connect(addr).await.map_err(|e| {
-        error!("Failed to connect to server at {}: {}", addr, e);
-        e.into()
-    })
+#[cfg(test)]
+mod tests {
+    use shared::{
+        models::{
+            fractal::{fractal_descriptor::FractalDescriptor, mandelbrot::Mandelbrot},
+            point::Point,
+            range::Range,
+            resolution::Resolution,
+            u8_data::U8Data,
+        },
+        networking::{read_message_raw, signature::sign_payload},
+    };
+    use tokio::{
+        io::AsyncReadExt,
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    fn sample_task(resolution: Resolution) -> FragmentTask {
+        FragmentTask::new(
+            U8Data::new(0, 16),
+            FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            64,
+            resolution,
+            Range::new(Point::new(-1.2, -1.2), Point::new(1.2, 1.2)),
+        )
+    }
+
+    async fn send_task<S: AsyncWrite + Unpin>(socket: &mut S, task: &FragmentTask) {
+        let task_json = serde_json::to_string(&task.to_json().unwrap()).unwrap();
+        let signature = sign_payload(env::secret(), task_json.as_bytes(), &[]);
+        send_message(
+            socket,
+            task_json.as_bytes(),
+            Some(&signature),
+            Compression::None,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn tasks_over_the_ceiling_are_clamped_on_receipt() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let mut task = sample_task(Resolution::new(4, 4));
+        task.max_iteration = u32::MAX;
+        send_task(&mut server, &task).await;
+        let received = read_fragment_task(&mut client, Duration::from_secs(1), 1000)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(received.max_iteration, 1000);
+    }
+
+    #[tokio::test]
+    async fn shutdown_completes_the_task_in_progress() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let worker = Worker::new(
+            "worker".to_string(),
+            500,
+            addr.ip().to_string(),
+            addr.port(),
+        );
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(run_worker(worker, shutdown.clone()));
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let request = read_message_raw(&mut socket).await.unwrap();
+        assert!(FragmentRequest::from_json(&request.json_message).is_ok());
+
+        // The shutdown comes in while the worker waits for the task it requested
+        shutdown.cancel();
+        send_task(&mut socket, &sample_task(Resolution::new(4, 4))).await;
+
+        let result = read_message_raw(&mut socket).await.unwrap();
+        assert!(FragmentResult::from_json(&result.json_message).is_ok());
+
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("worker did not shut down")
+            .unwrap();
+        let mut buffer = [0u8; 1];
+        assert_eq!(socket.read(&mut buffer).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn tasks_are_served_on_a_single_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let worker = Worker::new(
+            "worker".to_string(),
+            500,
+            addr.ip().to_string(),
+            addr.port(),
+        );
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(run_worker(worker, shutdown.clone()));
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        for index in 0..3 {
+            let request = read_message_raw(&mut socket).await.unwrap();
+            assert!(FragmentRequest::from_json(&request.json_message).is_ok());
+            if index == 2 {
+                shutdown.cancel();
+            }
+            let task = sample_task(Resolution::new(4, 4));
+            send_task(&mut socket, &task).await;
+
+            let message = read_message_raw(&mut socket).await.unwrap();
+            let result = FragmentResult::from_json(&message.json_message).unwrap();
+            assert_eq!(result.task_uuid, Some(task.task_uuid));
+        }
+
+        handle.await.unwrap();
+        let mut buffer = [0u8; 1];
+        assert_eq!(socket.read(&mut buffer).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_server_is_retried_until_the_retries_run_out() {
+        // nothing listens on the port once the listener is dropped
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut worker = Worker::new(
+            "worker".to_string(),
+            500,
+            addr.ip().to_string(),
+            addr.port(),
+        );
+        worker.retry_delay = Duration::from_millis(20);
+        worker.max_retry_delay = Duration::from_millis(80);
+        worker.max_retries = 4;
+
+        let start = tokio::time::Instant::now();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            run_worker(worker, CancellationToken::new()),
+        )
+        .await
+        .expect("worker did not give up");
+
+        // 20 + 40 + 80 + 80, the delay doubles up to its ceiling
+        assert!(start.elapsed() >= Duration::from_millis(220));
+    }
+
+    #[tokio::test]
+    async fn a_server_without_tasks_is_polled_past_the_retry_budget() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut worker = Worker::new(
+            "worker".to_string(),
+            500,
+            addr.ip().to_string(),
+            addr.port(),
+        );
+        worker.retry_delay = Duration::from_millis(1);
+        worker.max_retry_delay = Duration::from_millis(5);
+        worker.max_retries = 3;
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(run_worker(worker, shutdown.clone()));
+
+        // a paused server closes the connection instead of handing out a task
+        for _ in 0..10 {
+            let (mut socket, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept())
+                .await
+                .expect("worker gave up polling")
+                .unwrap();
+            read_message_raw(&mut socket).await.unwrap();
+        }
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        read_message_raw(&mut socket).await.unwrap();
+        shutdown.cancel();
+        send_task(&mut socket, &sample_task(Resolution::new(4, 4))).await;
+
+        let result = read_message_raw(&mut socket).await.unwrap();
+        assert!(FragmentResult::from_json(&result.json_message).is_ok());
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_result_computed_during_an_outage_is_delivered_on_the_next_connection() {
+        let mut worker = Worker::new("worker".to_string(), 500, "localhost".to_string(), 0);
+        let mut pending_results = ResultQueue::new(MAX_PENDING_RESULTS);
+        let mut tile_cache = TileCache::new(worker.tile_cache_capacity);
+        let mut backoff = Backoff::new(
+            worker.retry_delay,
+            worker.max_retry_delay,
+            worker.max_retries,
+        );
+        let shutdown = CancellationToken::new();
+        let task = sample_task(Resolution::new(4, 4));
+
+        // the server goes away right after handing out the task
+        let (stream, mut server) = tokio::io::duplex(1 << 16);
+        let outage = async {
+            read_message_raw(&mut server).await.unwrap();
+            send_task(&mut server, &task).await;
+            drop(server);
+        };
+        let (outcome, ()) = tokio::join!(
+            work(
+                stream,
+                &mut worker,
+                &mut pending_results,
+                &mut tile_cache,
+                &mut backoff,
+                &shutdown,
+            ),
+            outage
+        );
+        assert!(outcome.is_err());
+        assert_eq!(pending_results.len(), 1);
+
+        let (stream, mut server) = tokio::io::duplex(1 << 16);
+        shutdown.cancel();
+        work(
+            stream,
+            &mut worker,
+            &mut pending_results,
+            &mut tile_cache,
+            &mut backoff,
+            &shutdown,
+        )
+        .await
+        .unwrap();
+
+        let message = read_message_raw(&mut server).await.unwrap();
+        let result = FragmentResult::from_json(&message.json_message).unwrap();
+        assert_eq!(result.task_uuid, Some(task.task_uuid));
+        assert!(pending_results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn heartbeats_are_sent_while_a_task_is_computed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut worker = Worker::new(
+            "worker".to_string(),
+            500,
+            addr.ip().to_string(),
+            addr.port(),
+        );
+        worker.heartbeat_interval = Duration::from_millis(1);
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(run_worker(worker, shutdown.clone()));
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        read_message_raw(&mut socket).await.unwrap();
+        shutdown.cancel();
+        send_task(&mut socket, &sample_task(Resolution::new(400, 400))).await;
+
+        let mut heartbeats = 0;
+        loop {
+            let message = read_message_raw(&mut socket).await.unwrap();
+            if let Ok(heartbeat) = Heartbeat::from_json(&message.json_message) {
+                assert_eq!(heartbeat.worker_name, "worker");
+                heartbeats += 1;
+            } else {
+                assert!(FragmentResult::from_json(&message.json_message).is_ok());
+                break;
+            }
+        }
+
+        assert!(heartbeats > 0);
+        handle.await.unwrap();
+    }
 }
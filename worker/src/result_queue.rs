@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+
+use shared::models::fragments::fragment_result::FragmentResult;
+
+#[derive(Debug, Clone)]
+pub struct PendingResult {
+    pub result: FragmentResult,
+    pub data: Vec<u8>,
+}
+
+impl PendingResult {
+    pub fn new(result: FragmentResult, data: Vec<u8>) -> Self {
+        Self { result, data }
+    }
+}
+
+/// Bounded FIFO of results that could not be delivered to the server.
+///
+/// When the queue is full the oldest result is dropped to make room.
+#[derive(Debug)]
+pub struct ResultQueue {
+    capacity: usize,
+    results: VecDeque<PendingResult>,
+}
+
+impl ResultQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            results: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Queues a result, returning the oldest one if it had to be evicted.
+    pub fn push(&mut self, pending: PendingResult) -> Option<PendingResult> {
+        if self.capacity == 0 {
+            return Some(pending);
+        }
+
+        let dropped = if self.results.len() >= self.capacity {
+            self.results.pop_front()
+        } else {
+            None
+        };
+        self.results.push_back(pending);
+        dropped
+    }
+
+    /// Puts a result back at the head of the queue after a failed delivery, returning the
+    /// oldest queued result if it had to be evicted.
+    pub fn requeue(&mut self, pending: PendingResult) -> Option<PendingResult> {
+        if self.capacity == 0 {
+            return Some(pending);
+        }
+
+        let dropped = if self.results.len() >= self.capacity {
+            self.results.pop_front()
+        } else {
+            None
+        };
+        self.results.push_front(pending);
+        dropped
+    }
+
+    pub fn pop(&mut self) -> Option<PendingResult> {
+        self.results.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::models::{
+        pixel::pixel_data::PixelData, point::Point, range::Range, resolution::Resolution,
+        u8_data::U8Data,
+    };
+
+    use super::*;
+
+    fn pending(id: u8) -> PendingResult {
+        let result = FragmentResult::new(
+            U8Data::new(0, 16),
+            Resolution::new(1, 1),
+            Range::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0)),
+            PixelData::new(16, 1),
+        );
+        PendingResult::new(result, vec![id])
+    }
+
+    fn ids(queue: &mut ResultQueue) -> Vec<u8> {
+        std::iter::from_fn(|| queue.pop())
+            .map(|pending| pending.data[0])
+            .collect()
+    }
+
+    #[test]
+    fn the_oldest_result_is_dropped_when_full() {
+        let mut queue = ResultQueue::new(2);
+        assert!(queue.push(pending(1)).is_none());
+        assert!(queue.push(pending(2)).is_none());
+
+        let dropped = queue.push(pending(3)).unwrap();
+
+        assert_eq!(dropped.data, [1]);
+        assert_eq!(ids(&mut queue), [2, 3]);
+    }
+
+    #[test]
+    fn a_requeued_result_evicts_the_oldest_queued_one_when_full() {
+        let mut queue = ResultQueue::new(2);
+        queue.push(pending(1));
+        queue.push(pending(2));
+        let popped = queue.pop().unwrap();
+        queue.push(pending(3));
+
+        let dropped = queue.requeue(popped).unwrap();
+
+        assert_eq!(dropped.data, [2]);
+        assert_eq!(ids(&mut queue), [1, 3]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+};
+
+use shared::models::fragments::{fragment_result::FragmentResult, fragment_task::FragmentTask};
+use uuid::Uuid;
+
+use crate::result_queue::PendingResult;
+
+/// Least recently used results of the tasks computed by a worker, keyed by everything
+/// that shapes the pixels of a task.
+#[derive(Debug)]
+pub struct TileCache {
+    capacity: usize,
+    entries: VecDeque<(u64, PendingResult)>,
+    hits: u64,
+}
+
+impl TileCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            hits: 0,
+        }
+    }
+
+    /// The result computed earlier for an identical task, if it is still cached, stamped with
+    /// the uuid of `task`.
+    pub fn get(&mut self, task: &FragmentTask) -> Option<(FragmentResult, Vec<u8>)> {
+        let key = task_key(task)?;
+        let position = self.entries.iter().position(|(k, _)| *k == key)?;
+        let entry = self.entries.remove(position)?;
+        self.hits += 1;
+
+        let pending = entry.1.clone();
+        self.entries.push_back(entry);
+        Some((pending.result.for_task(task.task_uuid), pending.data))
+    }
+
+    /// Caches the result of `task`, evicting the least recently used one when full.
+    pub fn insert(&mut self, task: &FragmentTask, result: FragmentResult, data: Vec<u8>) {
+        let Some(key) = task_key(task) else {
+            return;
+        };
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries
+            .push_back((key, PendingResult::new(result, data)));
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// neither the uuid of the task nor the name the worker was registered under bear on the pixels
+fn task_key(task: &FragmentTask) -> Option<u64> {
+    let mut task = task.clone();
+    task.task_uuid = Uuid::nil();
+    task.assigned_name = None;
+    let serialized = serde_json::to_string(&task).ok()?;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serialized.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::models::{
+        fractal::{fractal_descriptor::FractalDescriptor, mandelbrot::Mandelbrot},
+        point::Point,
+        range::Range,
+        resolution::Resolution,
+        u8_data::U8Data,
+    };
+
+    use super::*;
+
+    fn task(max_iteration: u32) -> FragmentTask {
+        FragmentTask::new(
+            U8Data::new(0, 16),
+            FractalDescriptor::Mandelbrot(Mandelbrot::new()),
+            max_iteration,
+            Resolution::new(8, 8),
+            Range::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)),
+        )
+    }
+
+    fn perform(cache: &mut TileCache, task: &FragmentTask) -> Vec<u8> {
+        if let Some((_, data)) = cache.get(task) {
+            return data;
+        }
+        let (result, data) = task.perform().unwrap();
+        cache.insert(task, result, data.clone());
+        data
+    }
+
+    #[test]
+    fn a_repeated_task_is_served_from_the_cache() {
+        let mut cache = TileCache::new(4);
+
+        let computed = perform(&mut cache, &task(64));
+        let cached = perform(&mut cache, &task(64));
+
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cached, computed);
+    }
+
+    #[test]
+    fn a_different_max_iteration_misses() {
+        let mut cache = TileCache::new(4);
+
+        perform(&mut cache, &task(64));
+        perform(&mut cache, &task(128));
+
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn the_least_recently_used_result_is_evicted() {
+        let mut cache = TileCache::new(2);
+
+        perform(&mut cache, &task(16));
+        perform(&mut cache, &task(32));
+        perform(&mut cache, &task(16));
+        perform(&mut cache, &task(64));
+
+        assert!(cache.get(&task(16)).is_some());
+        assert!(cache.get(&task(32)).is_none());
+        assert_eq!(cache.len(), 2);
+    }
+}